@@ -11,10 +11,19 @@ function showTab(tabName) {
     if (tabContentElement)
         tabContentElement.classList.add('active');
 }
+function formOptions(type, names) {
+    const params = new URLSearchParams();
+    document.querySelectorAll(`#${type}-form select.option`).forEach(select => {
+        if (select.value && (!names || names.includes(select.name)))
+            params.append(select.name, select.value);
+    });
+    return params;
+}
 function processCSV(file, type) {
     const formData = new FormData();
     formData.append('file', file);
-    fetch(`/process-csv/${type}`, {
+    const params = formOptions(type);
+    fetch(`/process-csv/${type}?${params.toString()}`, {
         method: 'POST',
         body: formData
     })
@@ -29,7 +38,106 @@ function processCSV(file, type) {
         alert('エラーが発生しました。');
     });
 }
+// これまでに取り込んで保存した明細をまとめて表示する
+function loadHistory(type) {
+    const params = formOptions(type, ['group']);
+    fetch(`/history/${type}?${params.toString()}`)
+        .then(response => response.text())
+        .then(html => {
+        const resultContainer = document.getElementById(`${type}-result-container`);
+        if (resultContainer)
+            resultContainer.innerHTML = html;
+    })
+        .catch(error => {
+        console.error('Error:', error);
+        alert('エラーが発生しました。');
+    });
+}
+// 保存済みの配当金を銘柄・年ごとに集計し、月ごとの表の下に表示する
+function loadDividendSummary() {
+    fetch('/dividend-summary')
+        .then(response => response.text())
+        .then(html => {
+        const summaryContainer = document.getElementById('dividend-summary-container');
+        if (summaryContainer)
+            summaryContainer.innerHTML = html;
+    })
+        .catch(error => {
+        console.error('Error:', error);
+        alert('エラーが発生しました。');
+    });
+}
+// 入金履歴から推定した今後12か月の配当の入金予定を表示する
+function loadDividendCalendar() {
+    fetch('/dividend-calendar')
+        .then(response => response.text())
+        .then(html => {
+        const calendarContainer = document.getElementById('dividend-calendar-container');
+        if (calendarContainer)
+            calendarContainer.innerHTML = html;
+    })
+        .catch(error => {
+        console.error('Error:', error);
+        alert('エラーが発生しました。');
+    });
+}
+// 保存済みの約定履歴から、NISAの投資枠の年ごとの利用状況を表示する
+function loadNisaLimit() {
+    fetch('/nisa-limit')
+        .then(response => response.text())
+        .then(html => {
+        const nisaLimitContainer = document.getElementById('nisa-limit-container');
+        if (nisaLimitContainer)
+            nisaLimitContainer.innerHTML = html;
+    })
+        .catch(error => {
+        console.error('Error:', error);
+        alert('エラーが発生しました。');
+    });
+}
+年ごとに集計し、外国税額控除の対象額を表示する
+function loadForeignTaxCredit() {
+    fetch('/foreign-tax-credit')
+        .then(response => response.text())
+        .then(html => {
+        const creditContainer = document.getElementById('foreign-tax-credit-container');
+        if (creditContainer)
+            creditContainer.innerHTML = html;
+    })
+        .catch(error => {
+        console.error('Error:', error);
+        alert('エラーが発生しました。');
+    });
+}
+function processNetting() {
+    var _a, _b;
+    const dividendInput = document.getElementById('netting-dividend-csv');
+    const profitLossInput = document.getElementById('netting-profit-loss-csv');
+    const dividendFile = (_a = dividendInput === null || dividendInput === void 0 ? void 0 : dividendInput.files) === null || _a === void 0 ? void 0 : _a[0];
+    const profitLossFile = (_b = profitLossInput === null || profitLossInput === void 0 ? void 0 : profitLossInput.files) === null || _b === void 0 ? void 0 : _b[0];
+    if (!dividendFile || !profitLossFile)
+        return;
+    const formData = new FormData();
+    formData.append('dividend', dividendFile);
+    formData.append('profit-loss', profitLossFile);
+    const params = formOptions('netting');
+    fetch(`/process-netting?${params.toString()}`, {
+        method: 'POST',
+        body: formData
+    })
+        .then(response => response.text())
+        .then(html => {
+        const resultContainer = document.getElementById('netting-result-container');
+        if (resultContainer)
+            resultContainer.innerHTML = html;
+    })
+        .catch(error => {
+        console.error('Error:', error);
+        alert('エラーが発生しました。');
+    });
+}
 document.addEventListener('DOMContentLoaded', () => {
+    var _a, _b;
     const dividendInput = document.getElementById('dividend-csv');
     dividendInput === null || dividendInput === void 0 ? void 0 : dividendInput.addEventListener('change', (event) => {
         var _a;
@@ -48,4 +156,45 @@ document.addEventListener('DOMContentLoaded', () => {
             processCSV(file, 'profit-loss');
         }
     });
+    const tradeHistoryInput = document.getElementById('trade-history-csv');
+    tradeHistoryInput === null || tradeHistoryInput === void 0 ? void 0 : tradeHistoryInput.addEventListener('change', (event) => {
+        var _a;
+        const target = event.target;
+        const file = (_a = target.files) === null || _a === void 0 ? void 0 : _a[0];
+        if (file) {
+            processCSV(file, 'trade-history');
+        }
+    });
+    const holdingsInput = document.getElementById('holdings-csv');
+    holdingsInput === null || holdingsInput === void 0 ? void 0 : holdingsInput.addEventListener('change', (event) => {
+        var _a;
+        const target = event.target;
+        const file = (_a = target.files) === null || _a === void 0 ? void 0 : _a[0];
+        if (file) {
+            processCSV(file, 'holdings');
+        }
+    });
+    const fxRateInput = document.getElementById('fx-rate-csv');
+    fxRateInput === null || fxRateInput === void 0 ? void 0 : fxRateInput.addEventListener('change', (event) => {
+        var _a;
+        const target = event.target;
+        const file = (_a = target.files) === null || _a === void 0 ? void 0 : _a[0];
+        if (file) {
+            processCSV(file, 'fx-rate');
+        }
+    });
+    // 小計の単位を変えたら、選択済みのファイルを読み込み直す
+    ['dividend', 'profit-loss'].forEach(type => {
+        var _a;
+        (_a = document.getElementById(`${type}-group`)) === null || _a === void 0 ? void 0 : _a.addEventListener('change', () => {
+            var _a;
+            const input = document.getElementById(`${type}-csv`);
+            const file = (_a = input === null || input === void 0 ? void 0 : input.files) === null || _a === void 0 ? void 0 : _a[0];
+            if (file)
+                processCSV(file, type);
+        });
+    });
+    // 配当金と実現損益の両方が選択された時点で損益通算を計算する
+    (_a = document.getElementById('netting-dividend-csv')) === null || _a === void 0 ? void 0 : _a.addEventListener('change', processNetting);
+    (_b = document.getElementById('netting-profit-loss-csv')) === null || _b === void 0 ? void 0 : _b.addEventListener('change', processNetting);
 });
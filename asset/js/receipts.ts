@@ -15,11 +15,22 @@ function showTab(tabName: string): void {
 
 }
 
+function formOptions(type: string, names?: string[]): URLSearchParams {
+    const params = new URLSearchParams();
+    document.querySelectorAll<HTMLSelectElement>(`#${type}-form select.option`).forEach(select => {
+        if (select.value && (!names || names.includes(select.name)))
+            params.append(select.name, select.value);
+    });
+    return params;
+}
+
 function processCSV(file: File, type: string): void {
     const formData = new FormData();
     formData.append('file', file);
 
-    fetch(`/process-csv/${type}`, {
+    const params = formOptions(type);
+
+    fetch(`/process-csv/${type}?${params.toString()}`, {
         method: 'POST',
         body: formData
     })
@@ -35,6 +46,112 @@ function processCSV(file: File, type: string): void {
         });
 }
 
+// これまでに取り込んで保存した明細をまとめて表示する
+function loadHistory(type: string): void {
+    const params = formOptions(type, ['group']);
+    fetch(`/history/${type}?${params.toString()}`)
+        .then(response => response.text())
+        .then(html => {
+            const resultContainer = document.getElementById(`${type}-result-container`);
+            if (resultContainer)
+                resultContainer.innerHTML = html;
+        })
+        .catch(error => {
+            console.error('Error:', error);
+            alert('エラーが発生しました。');
+        });
+}
+
+// 保存済みの配当金を銘柄・年ごとに集計し、月ごとの表の下に表示する
+function loadDividendSummary(): void {
+    fetch('/dividend-summary')
+        .then(response => response.text())
+        .then(html => {
+            const summaryContainer = document.getElementById('dividend-summary-container');
+            if (summaryContainer)
+                summaryContainer.innerHTML = html;
+        })
+        .catch(error => {
+            console.error('Error:', error);
+            alert('エラーが発生しました。');
+        });
+}
+
+// 入金履歴から推定した今後12か月の配当の入金予定を表示する
+function loadDividendCalendar(): void {
+    fetch('/dividend-calendar')
+        .then(response => response.text())
+        .then(html => {
+            const calendarContainer = document.getElementById('dividend-calendar-container');
+            if (calendarContainer)
+                calendarContainer.innerHTML = html;
+        })
+        .catch(error => {
+            console.error('Error:', error);
+            alert('エラーが発生しました。');
+        });
+}
+
+// 保存済みの約定履歴から、NISAの投資枠の年ごとの利用状況を表示する
+function loadNisaLimit(): void {
+    fetch('/nisa-limit')
+        .then(response => response.text())
+        .then(html => {
+            const nisaLimitContainer = document.getElementById('nisa-limit-container');
+            if (nisaLimitContainer)
+                nisaLimitContainer.innerHTML = html;
+        })
+        .catch(error => {
+            console.error('Error:', error);
+            alert('エラーが発生しました。');
+        });
+}
+
+// 課税口座の配当の外国源泉税を年ごとに集計し、外国税額控除の対象額を表示する
+function loadForeignTaxCredit(): void {
+    fetch('/foreign-tax-credit')
+        .then(response => response.text())
+        .then(html => {
+            const creditContainer = document.getElementById('foreign-tax-credit-container');
+            if (creditContainer)
+                creditContainer.innerHTML = html;
+        })
+        .catch(error => {
+            console.error('Error:', error);
+            alert('エラーが発生しました。');
+        });
+}
+
+function processNetting(): void {
+    const dividendInput = document.getElementById('netting-dividend-csv') as HTMLInputElement | null;
+    const profitLossInput = document.getElementById('netting-profit-loss-csv') as HTMLInputElement | null;
+    const dividendFile = dividendInput?.files?.[0];
+    const profitLossFile = profitLossInput?.files?.[0];
+    if (!dividendFile || !profitLossFile)
+        return;
+
+    const formData = new FormData();
+    formData.append('dividend', dividendFile);
+    formData.append('profit-loss', profitLossFile);
+
+    const params = formOptions('netting');
+
+    fetch(`/process-netting?${params.toString()}`, {
+        method: 'POST',
+        body: formData
+    })
+        .then(response => response.text())
+        .then(html => {
+            const resultContainer = document.getElementById('netting-result-container');
+            if (resultContainer)
+                resultContainer.innerHTML = html;
+        })
+        .catch(error => {
+            console.error('Error:', error);
+            alert('エラーが発生しました。');
+        });
+}
+
 document.addEventListener('DOMContentLoaded', () => {
     const dividendInput = document.getElementById('dividend-csv');
     dividendInput?.addEventListener('change', (event) => {
@@ -53,4 +170,45 @@ document.addEventListener('DOMContentLoaded', () => {
             processCSV(file, 'profit-loss');
         }
     });
+
+    const tradeHistoryInput = document.getElementById('trade-history-csv');
+    tradeHistoryInput?.addEventListener('change', (event) => {
+        const target = event.target as HTMLInputElement;
+        const file = target.files?.[0];
+        if (file) {
+            processCSV(file, 'trade-history');
+        }
+    });
+
+    const holdingsInput = document.getElementById('holdings-csv');
+    holdingsInput?.addEventListener('change', (event) => {
+        const target = event.target as HTMLInputElement;
+        const file = target.files?.[0];
+        if (file) {
+            processCSV(file, 'holdings');
+        }
+    });
+
+    const fxRateInput = document.getElementById('fx-rate-csv');
+    fxRateInput?.addEventListener('change', (event) => {
+        const target = event.target as HTMLInputElement;
+        const file = target.files?.[0];
+        if (file) {
+            processCSV(file, 'fx-rate');
+        }
+    });
+
+    // 小計の単位を変えたら、選択済みのファイルを読み込み直す
+    ['dividend', 'profit-loss'].forEach(type => {
+        document.getElementById(`${type}-group`)?.addEventListener('change', () => {
+            const input = document.getElementById(`${type}-csv`) as HTMLInputElement | null;
+            const file = input?.files?.[0];
+            if (file)
+                processCSV(file, type);
+        });
+    });
+
+    // 配当金と実現損益の両方が選択された時点で損益通算を計算する
+    document.getElementById('netting-dividend-csv')?.addEventListener('change', processNetting);
+    document.getElementById('netting-profit-loss-csv')?.addEventListener('change', processNetting);
 });
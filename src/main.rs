@@ -1,16 +1,29 @@
 mod setting;
 use actix_files as fs;
 use actix_multipart::Multipart;
-use actix_web::{web, App, Error, HttpResponse, HttpServer, Result};
+use actix_session::{storage::CookieSessionStore, Session, SessionMiddleware};
+use actix_web::{cookie::Key, http::header, web, App, Error, HttpResponse, HttpServer, Result};
 use futures::StreamExt;
 use futures_util::TryStreamExt;
-use std::{env, sync::Arc};
+use std::{collections::HashMap, env, sync::Arc};
 use tera::Tera;
 
 mod services;
-use services::factroy;
+use services::{
+    auth::{self, Credentials, User},
+    dividend_calendar::lib::DividendCalendarManager,
+    dividend_summary::lib::DividendSummaryManager,
+    factroy,
+    foreign_tax_credit::lib::ForeignTaxCreditManager,
+    loss_carry_forward::lib::{FilingForm, LossCarryForwardManager},
+    netting::lib::NettingManager,
+    nisa_limit::lib::NisaLimitManager,
+    store::lib::{HistoryQuery, Store},
+    tax_return::lib::{TaxReturnManager, TaxReturnQuery},
+    templete::ReportOptions,
+};
 
-async fn process_csv(mut payload: Multipart, path: web::Path<String>) -> Result<String, Error> {
+async fn read_payload(mut payload: Multipart) -> Result<Vec<u8>, Error> {
     let mut field = match payload.try_next().await {
         Ok(Some(field)) => field,
         Ok(None) => return Err(actix_web::error::ErrorBadRequest("No file in payload")),
@@ -23,45 +36,605 @@ async fn process_csv(mut payload: Multipart, path: web::Path<String>) -> Result<
         bytes.extend_from_slice(&chunk);
     }
 
+    Ok(bytes)
+}
+
+// 複数のファイルを送るフォーム用に、フィールド名ごとの内容を読み込む
+async fn read_payload_fields(mut payload: Multipart) -> Result<HashMap<String, Vec<u8>>, Error> {
+    let mut fields = HashMap::new();
+    while let Some(mut field) = payload
+        .try_next()
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk =
+                chunk.map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+            bytes.extend_from_slice(&chunk);
+        }
+        fields.insert(name, bytes);
+    }
+
+    Ok(fields)
+}
+
+fn take_field(fields: &mut HashMap<String, Vec<u8>>, name: &str) -> Result<Vec<u8>, Error> {
+    fields
+        .remove(name)
+        .ok_or_else(|| actix_web::error::ErrorBadRequest(format!("No '{name}' file in payload")))
+}
+
+// ログイン中のユーザー。未ログインなら401を返す
+fn current_user(session: &Session) -> Result<User, Error> {
+    session
+        .get::<User>("user")
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?
+        .ok_or_else(|| actix_web::error::ErrorUnauthorized("Login required"))
+}
+
+fn redirect(location: &str) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, location))
+        .finish()
+}
+
+// SQLiteの読み書きやパスワードのハッシュ計算はスレッドを止めるため、actixのワーカーではなく
+// ブロッキング処理用のスレッドプールで実行する
+async fn run_blocking<T, F>(f: F) -> Result<T, Error>
+where
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    web::block(f)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))
+}
+
+async fn process_csv(
+    payload: Multipart,
+    path: web::Path<String>,
+    query: web::Query<ReportOptions>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<String, Error> {
+    let user = current_user(&session)?;
+    let bytes = read_payload(payload).await?;
+
     let csv_type = path.into_inner();
-    let manager = factroy::create_factory(&csv_type);
-    let result = manager
-        .execute(bytes)
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+    let manager = factroy::create_factory(&csv_type, query.broker.as_deref(), query.group)
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+    let (store, tmpl) = (store.get_ref().clone(), tmpl.get_ref().clone());
+    run_blocking(move || manager.execute(bytes, &query, &store.for_user(user.id), &tmpl)).await
+}
+
+async fn process_csv_json(
+    payload: Multipart,
+    path: web::Path<String>,
+    query: web::Query<ReportOptions>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let bytes = read_payload(payload).await?;
+
+    let csv_type = path.into_inner();
+    let manager = factroy::create_factory(&csv_type, query.broker.as_deref(), query.group)
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+    let store = store.get_ref().clone();
+    let result =
+        run_blocking(move || manager.execute_json(bytes, &query, &store.for_user(user.id))).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
 
-    Ok(result)
+async fn history(
+    path: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<String, Error> {
+    let user = current_user(&session)?;
+    let csv_type = path.into_inner();
+    let manager = factroy::create_factory(&csv_type, None, query.group)
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+    let (store, tmpl) = (store.get_ref().clone(), tmpl.get_ref().clone());
+    run_blocking(move || manager.history(&store.for_user(user.id), &query, &tmpl)).await
 }
 
-async fn index(tmpl: web::Data<Arc<Tera>>) -> Result<HttpResponse, Error> {
-    let context = tera::Context::new();
+async fn history_json(
+    path: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let csv_type = path.into_inner();
+    let manager = factroy::create_factory(&csv_type, None, query.group)
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+    let store = store.get_ref().clone();
+    let result =
+        run_blocking(move || manager.history_json(&store.for_user(user.id), &query)).await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+async fn process_netting(
+    payload: Multipart,
+    query: web::Query<ReportOptions>,
+    session: Session,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<String, Error> {
+    current_user(&session)?;
+    let mut fields = read_payload_fields(payload).await?;
+    let dividend_bytes = take_field(&mut fields, "dividend")?;
+    let profit_and_loss_bytes = take_field(&mut fields, "profit-loss")?;
+
+    let manager = NettingManager::new(query.broker.as_deref())
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+    let tmpl = tmpl.get_ref().clone();
+    run_blocking(move || manager.execute(dividend_bytes, profit_and_loss_bytes, &query, &tmpl))
+        .await
+}
+
+async fn process_netting_json(
+    payload: Multipart,
+    query: web::Query<ReportOptions>,
+    session: Session,
+) -> Result<HttpResponse, Error> {
+    current_user(&session)?;
+    let mut fields = read_payload_fields(payload).await?;
+    let dividend_bytes = take_field(&mut fields, "dividend")?;
+    let profit_and_loss_bytes = take_field(&mut fields, "profit-loss")?;
+
+    let manager = NettingManager::new(query.broker.as_deref())
+        .map_err(|e| actix_web::error::ErrorBadRequest(e.to_string()))?;
+    let result =
+        run_blocking(move || manager.execute_json(dividend_bytes, profit_and_loss_bytes, &query))
+            .await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+async fn index(session: Session, tmpl: web::Data<Arc<Tera>>) -> Result<HttpResponse, Error> {
+    let mut context = tera::Context::new();
+    context.insert("user", &session.get::<User>("user").ok().flatten());
     let rendered = tmpl
         .render("index.html", &context)
         .map_err(|_| actix_web::error::ErrorInternalServerError("Template error"))?;
     Ok(HttpResponse::Ok().content_type("text/html").body(rendered))
 }
 
-async fn receipts(tmpl: web::Data<Arc<Tera>>) -> Result<HttpResponse, Error> {
-    let context = tera::Context::new();
+async fn receipts(session: Session, tmpl: web::Data<Arc<Tera>>) -> Result<HttpResponse, Error> {
+    let Ok(user) = current_user(&session) else {
+        return Ok(redirect("/login"));
+    };
+    let mut context = tera::Context::new();
+    context.insert("user", &user);
     let rendered = tmpl
         .render("receipts.html", &context)
         .map_err(|_| actix_web::error::ErrorInternalServerError("Template error"))?;
     Ok(HttpResponse::Ok().content_type("text/html").body(rendered))
 }
 
+fn load_tax_return(store: &Store, user: &User) -> anyhow::Result<TaxReturnManager> {
+    let manager = TaxReturnManager::new();
+    manager.load(&store.for_user(user.id))?;
+    Ok(manager)
+}
+
+async fn tax_return(
+    query: web::Query<TaxReturnQuery>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<HttpResponse, Error> {
+    let Ok(user) = current_user(&session) else {
+        return Ok(redirect("/login"));
+    };
+    let (store, report_tmpl) = (store.get_ref().clone(), tmpl.get_ref().clone());
+    let (year, years, report) = run_blocking(move || {
+        let manager = load_tax_return(&store, &user)?;
+        let year = manager.resolve_year(&query);
+        let report = manager.generate_html_table(year, &report_tmpl)?;
+        Ok((year, manager.years(), report))
+    })
+    .await?;
+
+    let mut context = tera::Context::new();
+    context.insert("user", &current_user(&session)?);
+    context.insert("year", &year);
+    context.insert("years", &years);
+    context.insert("report", &report);
+    let rendered = tmpl
+        .render("tax_return.html", &context)
+        .map_err(|_| actix_web::error::ErrorInternalServerError("Template error"))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(rendered))
+}
+
+async fn tax_return_csv(
+    query: web::Query<TaxReturnQuery>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let store = store.get_ref().clone();
+    let (year, csv) = run_blocking(move || {
+        let manager = load_tax_return(&store, &user)?;
+        let year = manager.resolve_year(&query);
+        Ok((year, manager.generate_csv(year)?))
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"tax-return-{year}.csv\""),
+        ))
+        .body(csv))
+}
+
+async fn tax_return_json(
+    query: web::Query<TaxReturnQuery>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let store = store.get_ref().clone();
+    let result = run_blocking(move || {
+        let manager = load_tax_return(&store, &user)?;
+        let year = manager.resolve_year(&query);
+        Ok(manager.generate_json(year))
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+fn load_dividend_summary(
+    store: &Store,
+    user: &User,
+    query: &HistoryQuery,
+) -> anyhow::Result<DividendSummaryManager> {
+    let manager = DividendSummaryManager::new();
+    manager.load(&store.for_user(user.id), query)?;
+    Ok(manager)
+}
+
+async fn dividend_summary(
+    query: web::Query<HistoryQuery>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<String, Error> {
+    let user = current_user(&session)?;
+    let (store, tmpl) = (store.get_ref().clone(), tmpl.get_ref().clone());
+    run_blocking(move || load_dividend_summary(&store, &user, &query)?.generate_html_table(&tmpl))
+        .await
+}
+
+async fn dividend_summary_json(
+    query: web::Query<HistoryQuery>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let store = store.get_ref().clone();
+    let result =
+        run_blocking(move || Ok(load_dividend_summary(&store, &user, &query)?.generate_json()))
+            .await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+fn load_foreign_tax_credit(
+    store: &Store,
+    user: &User,
+    query: &HistoryQuery,
+) -> anyhow::Result<ForeignTaxCreditManager> {
+    let manager = ForeignTaxCreditManager::new();
+    manager.load(&store.for_user(user.id), query)?;
+    Ok(manager)
+}
+
+async fn foreign_tax_credit(
+    query: web::Query<HistoryQuery>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<String, Error> {
+    let user = current_user(&session)?;
+    let (store, tmpl) = (store.get_ref().clone(), tmpl.get_ref().clone());
+    run_blocking(move || load_foreign_tax_credit(&store, &user, &query)?.generate_html_table(&tmpl))
+        .await
+}
+
+async fn foreign_tax_credit_json(
+    query: web::Query<HistoryQuery>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let store = store.get_ref().clone();
+    let result =
+        run_blocking(move || Ok(load_foreign_tax_credit(&store, &user, &query)?.generate_json()))
+            .await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+fn load_dividend_calendar(store: &Store, user: &User) -> anyhow::Result<DividendCalendarManager> {
+    let manager = DividendCalendarManager::new();
+    manager.load(&store.for_user(user.id))?;
+    Ok(manager)
+}
+
+async fn dividend_calendar(
+    session: Session,
+    store: web::Data<Arc<Store>>,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<String, Error> {
+    let user = current_user(&session)?;
+    let (store, tmpl) = (store.get_ref().clone(), tmpl.get_ref().clone());
+    run_blocking(move || load_dividend_calendar(&store, &user)?.generate_html_table(&tmpl)).await
+}
+
+async fn dividend_calendar_csv(
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let store = store.get_ref().clone();
+    let csv = run_blocking(move || load_dividend_calendar(&store, &user)?.generate_csv()).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"dividend-calendar.csv\"",
+        ))
+        .body(csv))
+}
+
+async fn dividend_calendar_json(
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let store = store.get_ref().clone();
+    let result =
+        run_blocking(move || Ok(load_dividend_calendar(&store, &user)?.generate_json())).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+fn load_nisa_limit(store: &Store, user: &User) -> anyhow::Result<NisaLimitManager> {
+    let manager = NisaLimitManager::new();
+    manager.load(&store.for_user(user.id))?;
+    Ok(manager)
+}
+
+async fn nisa_limit(
+    session: Session,
+    store: web::Data<Arc<Store>>,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<String, Error> {
+    let user = current_user(&session)?;
+    let (store, tmpl) = (store.get_ref().clone(), tmpl.get_ref().clone());
+    run_blocking(move || load_nisa_limit(&store, &user)?.generate_html_table(&tmpl)).await
+}
+
+async fn nisa_limit_json(
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let store = store.get_ref().clone();
+    let result = run_blocking(move || Ok(load_nisa_limit(&store, &user)?.generate_json())).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+fn load_loss_carry_forward(store: &Store, user: &User) -> anyhow::Result<LossCarryForwardManager> {
+    let manager = LossCarryForwardManager::new();
+    manager.load(&store.for_user(user.id))?;
+    Ok(manager)
+}
+
+async fn loss_carry_forward(
+    session: Session,
+    store: web::Data<Arc<Store>>,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<HttpResponse, Error> {
+    let Ok(user) = current_user(&session) else {
+        return Ok(redirect("/login"));
+    };
+    let (store, report_tmpl) = (store.get_ref().clone(), tmpl.get_ref().clone());
+    let report_user = user.clone();
+    let report = run_blocking(move || {
+        load_loss_carry_forward(&store, &report_user)?.generate_html_table(&report_tmpl)
+    })
+    .await?;
+
+    let mut context = tera::Context::new();
+    context.insert("user", &user);
+    context.insert("report", &report);
+    let rendered = tmpl
+        .render("loss_carry_forward.html", &context)
+        .map_err(|_| actix_web::error::ErrorInternalServerError("Template error"))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(rendered))
+}
+
+async fn loss_carry_forward_filed(
+    form: web::Form<FilingForm>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let store = store.get_ref().clone();
+    run_blocking(move || store.for_user(user.id).set_filed(form.year, form.filed)).await?;
+    Ok(redirect("/loss-carry-forward"))
+}
+
+async fn loss_carry_forward_json(
+    session: Session,
+    store: web::Data<Arc<Store>>,
+) -> Result<HttpResponse, Error> {
+    let user = current_user(&session)?;
+    let store = store.get_ref().clone();
+    let result =
+        run_blocking(move || Ok(load_loss_carry_forward(&store, &user)?.generate_json())).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+fn render_login(tmpl: &Tera, error: Option<&str>) -> Result<String, Error> {
+    let mut context = tera::Context::new();
+    context.insert("error", &error);
+    tmpl.render("login.html", &context)
+        .map_err(|_| actix_web::error::ErrorInternalServerError("Template error"))
+}
+
+async fn login_page(tmpl: web::Data<Arc<Tera>>) -> Result<HttpResponse, Error> {
+    let rendered = render_login(&tmpl, None)?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(rendered))
+}
+
+fn start_session(session: &Session, user: &User) -> Result<HttpResponse, Error> {
+    session.renew();
+    session
+        .insert("user", user)
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+    Ok(redirect("/receipts"))
+}
+
+async fn login(
+    form: web::Form<Credentials>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<HttpResponse, Error> {
+    let store = store.get_ref().clone();
+    let credentials = form.into_inner();
+    let user = run_blocking(move || auth::authenticate(&store, &credentials)).await?;
+    match user {
+        Some(user) => start_session(&session, &user),
+        None => {
+            let rendered = render_login(&tmpl, Some("ユーザー名またはパスワードが違います"))?;
+            Ok(HttpResponse::Unauthorized()
+                .content_type("text/html")
+                .body(rendered))
+        }
+    }
+}
+
+async fn register(
+    form: web::Form<Credentials>,
+    session: Session,
+    store: web::Data<Arc<Store>>,
+    tmpl: web::Data<Arc<Tera>>,
+) -> Result<HttpResponse, Error> {
+    let store = store.get_ref().clone();
+    let credentials = form.into_inner();
+    let registered = web::block(move || auth::register(&store, &credentials))
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
+    match registered {
+        Ok(user) => start_session(&session, &user),
+        Err(e) => {
+            let rendered = render_login(&tmpl, Some(&e.to_string()))?;
+            Ok(HttpResponse::BadRequest()
+                .content_type("text/html")
+                .body(rendered))
+        }
+    }
+}
+
+async fn logout(session: Session) -> HttpResponse {
+    session.purge();
+    redirect("/")
+}
+
+// 再起動後もログイン状態を保つには、64バイト以上のSESSION_KEYを設定する
+fn session_key() -> Key {
+    match env::var("SESSION_KEY") {
+        Ok(key) => Key::try_from(key.as_bytes()).expect("SESSION_KEY must be at least 64 bytes"),
+        Err(_) => {
+            println!("SESSION_KEY is not set; sessions will not survive a restart");
+            Key::generate()
+        }
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
     let addr = format!("0.0.0.0:{}", port);
     println!("Starting server at: {}", addr);
     let tera = Arc::new(Tera::new("asset/html/**").unwrap());
+    let database_path = env::var("DATABASE_PATH").unwrap_or_else(|_| "shoken.db".to_string());
+    let store = Arc::new(Store::open(&database_path).unwrap());
+    let session_key = session_key();
 
     HttpServer::new(move || {
         App::new()
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                session_key.clone(),
+            ))
             .app_data(web::Data::new(tera.clone()))
+            .app_data(web::Data::new(store.clone()))
             .service(fs::Files::new("/js", "asset/js").show_files_listing())
             .service(fs::Files::new("/css", "asset/css").show_files_listing())
             .service(fs::Files::new("/img", "asset/img").show_files_listing())
             .route("/process-csv/{type}", web::post().to(process_csv))
+            .route(
+                "/api/v1/process-csv/{type}",
+                web::post().to(process_csv_json),
+            )
+            .route("/history/{type}", web::get().to(history))
+            .route("/api/v1/history/{type}", web::get().to(history_json))
+            .route("/process-netting", web::post().to(process_netting))
+            .route(
+                "/api/v1/process-netting",
+                web::post().to(process_netting_json),
+            )
+            .route("/dividend-summary", web::get().to(dividend_summary))
+            .route(
+                "/api/v1/dividend-summary",
+                web::get().to(dividend_summary_json),
+            )
+            .route("/dividend-calendar", web::get().to(dividend_calendar))
+            .route(
+                "/dividend-calendar/csv",
+                web::get().to(dividend_calendar_csv),
+            )
+            .route(
+                "/api/v1/dividend-calendar",
+                web::get().to(dividend_calendar_json),
+            )
+            .route("/nisa-limit", web::get().to(nisa_limit))
+            .route("/api/v1/nisa-limit", web::get().to(nisa_limit_json))
+            .route("/foreign-tax-credit", web::get().to(foreign_tax_credit))
+            .route(
+                "/api/v1/foreign-tax-credit",
+                web::get().to(foreign_tax_credit_json),
+            )
+            .route("/tax-return", web::get().to(tax_return))
+            .route("/tax-return/csv", web::get().to(tax_return_csv))
+            .route("/api/v1/tax-return", web::get().to(tax_return_json))
+            .route("/loss-carry-forward", web::get().to(loss_carry_forward))
+            .route(
+                "/loss-carry-forward/filed",
+                web::post().to(loss_carry_forward_filed),
+            )
+            .route(
+                "/api/v1/loss-carry-forward",
+                web::get().to(loss_carry_forward_json),
+            )
+            .route("/login", web::get().to(login_page))
+            .route("/login", web::post().to(login))
+            .route("/register", web::post().to(register))
+            .route("/logout", web::post().to(logout))
             .route("/", web::get().to(index))
             .route("/receipts", web::get().to(receipts))
     })
@@ -0,0 +1,97 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+// CSVの口座欄から判定する口座区分
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountType {
+    Specific,      // 特定口座（源泉徴収あり）
+    General,       // 一般口座（要申告）
+    NisaGrowth,    // NISA成長投資枠
+    NisaTsumitate, // NISAつみたて投資枠
+    OldNisa,       // 旧NISA（2023年までの一般NISA・つみたてNISA）
+    JuniorNisa,    // ジュニアNISA
+}
+
+impl AccountType {
+    // 合計を表示する順序
+    pub const ALL: [AccountType; 6] = [
+        AccountType::Specific,
+        AccountType::General,
+        AccountType::NisaGrowth,
+        AccountType::NisaTsumitate,
+        AccountType::OldNisa,
+        AccountType::JuniorNisa,
+    ];
+
+    // 証券会社ごとに表記の異なる口座名を判定する
+    // （例: 「特定口座」「NISA(成長投資枠)」「NISAつみたて投資枠」「NISA(つ)」「旧NISA」「ジュニアNISA」）。
+    // 判定できない口座は非課税として扱わないよう一般口座とみなす
+    pub fn parse(account: Option<&str>) -> Self {
+        let account = account.unwrap_or("").to_uppercase();
+        let is_nisa = account.contains("NISA") || account.contains("ＮＩＳＡ");
+        if account.contains("特定") {
+            AccountType::Specific
+        } else if account.contains("ジュニア") {
+            AccountType::JuniorNisa
+        } else if is_nisa && account.contains("旧") {
+            AccountType::OldNisa
+        } else if ["つみたて", "積立", "(つ", "（つ"]
+            .iter()
+            .any(|label| account.contains(label))
+        {
+            AccountType::NisaTsumitate
+        } else if is_nisa || account.contains("成長投資枠") {
+            AccountType::NisaGrowth
+        } else {
+            AccountType::General
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AccountType::Specific => "特定",
+            AccountType::General => "一般",
+            AccountType::NisaGrowth => "NISA成長投資枠",
+            AccountType::NisaTsumitate => "NISAつみたて投資枠",
+            AccountType::OldNisa => "旧NISA",
+            AccountType::JuniorNisa => "ジュニアNISA",
+        }
+    }
+
+    // 譲渡益・配当が課税される口座
+    pub fn is_taxable(&self) -> bool {
+        matches!(self, AccountType::Specific | AccountType::General)
+    }
+
+    // 譲渡益の税額が源泉徴収されず、確定申告が必要な口座
+    pub fn requires_self_filing(&self) -> bool {
+        matches!(self, AccountType::General)
+    }
+}
+
+// 口座区分ごとの金額の合計
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AccountTotals {
+    pub specific: Decimal,   // 特定口座
+    pub general: Decimal,    // 一般口座
+    pub tax_exempt: Decimal, // NISA（非課税）
+}
+
+impl AccountTotals {
+    pub fn add(&mut self, account_type: AccountType, amount: Decimal) {
+        match account_type {
+            AccountType::Specific => self.specific += amount,
+            AccountType::General => self.general += amount,
+            _ => self.tax_exempt += amount,
+        }
+    }
+
+    pub fn taxable(&self) -> Decimal {
+        self.specific + self.general
+    }
+
+    pub fn total(&self) -> Decimal {
+        self.taxable() + self.tax_exempt
+    }
+}
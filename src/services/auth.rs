@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+use super::store::lib::Store;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_USERNAME_LENGTH: usize = 32;
+
+// 存在しないユーザー名でも照合に同じだけ時間をかけ、応答時間からユーザー名の有無が分からないようにする
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash_password("dummy password").expect("Failed to hash dummy password"));
+
+// セッションに保存するログイン中のユーザー
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+}
+
+// ログイン・新規登録フォームの入力
+#[derive(Debug, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+pub fn register(store: &Store, credentials: &Credentials) -> Result<User> {
+    let username = credentials.username.trim();
+    if username.is_empty() || username.chars().count() > MAX_USERNAME_LENGTH {
+        return Err(anyhow!(
+            "ユーザー名は1〜{}文字で入力してください",
+            MAX_USERNAME_LENGTH
+        ));
+    }
+    if credentials.password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(anyhow!(
+            "パスワードは{}文字以上で入力してください",
+            MIN_PASSWORD_LENGTH
+        ));
+    }
+
+    let password_hash = hash_password(&credentials.password)?;
+    store
+        .insert_user(username, &password_hash)?
+        .ok_or_else(|| anyhow!("ユーザー名「{}」は既に使われています", username))
+}
+
+// ユーザー名とパスワードが一致しなければNoneを返す
+pub fn authenticate(store: &Store, credentials: &Credentials) -> Result<Option<User>> {
+    let found = store.find_user(credentials.username.trim())?;
+    let password_hash = found
+        .as_ref()
+        .map_or(DUMMY_PASSWORD_HASH.as_str(), |(_, password_hash)| {
+            password_hash.as_str()
+        });
+    let password_hash =
+        PasswordHash::new(password_hash).map_err(|e| anyhow!("Invalid password hash: {}", e))?;
+    let verified = Argon2::default()
+        .verify_password(credentials.password.as_bytes(), &password_hash)
+        .is_ok();
+    Ok(found.filter(|_| verified).map(|(user, _)| user))
+}
+
+fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow!("Failed to hash password: {}", e))?;
+    Ok(password_hash.to_string())
+}
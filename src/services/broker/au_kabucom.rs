@@ -0,0 +1,92 @@
+use super::lib::BrokerAdapter;
+use crate::services::csv::column_map::ColumnSpec;
+
+pub struct AuKabucom;
+
+impl AuKabucom {
+    const DIVIDEND_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::required("settlement_date", &["入金日"]),
+        ColumnSpec::optional("payment_date", &["支払確定日", "現地支払日"]),
+        ColumnSpec::optional("product", &["商品"]),
+        ColumnSpec::required("account", &["口座"]),
+        ColumnSpec::required("security_code", &["銘柄コード"]),
+        ColumnSpec::required("security_name", &["銘柄名"]),
+        ColumnSpec::optional("currency", &["通貨"]),
+        ColumnSpec::optional("unit_price", &["1株あたり配当金"]),
+        ColumnSpec::required("shares", &["数量"]),
+        ColumnSpec::required("dividends_before_tax", &["配当金額(税引前)"]),
+        ColumnSpec::required("taxes", &["源泉徴収税額"]),
+        ColumnSpec::optional(
+            "foreign_taxes",
+            &["外国源泉徴収税額", "外国源泉税額", "外国源泉税", "外国税額"],
+        ),
+        ColumnSpec::optional(
+            "domestic_taxes",
+            &["国内源泉徴収税額", "国内源泉税額", "国内源泉税", "国内税額"],
+        ),
+        ColumnSpec::required("net_amount_received", &["入金額"]),
+    ];
+
+    const PROFIT_AND_LOSS_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::required("trade_date", &["約定日"]),
+        ColumnSpec::optional("settlement_date", &["受渡日"]),
+        ColumnSpec::required("security_code", &["銘柄コード"]),
+        ColumnSpec::required("security_name", &["銘柄名"]),
+        ColumnSpec::required("account", &["口座"]),
+        ColumnSpec::required("shares", &["数量"]),
+        ColumnSpec::optional("asked_price", &["約定単価"]),
+        ColumnSpec::optional("proceeds", &["売却代金"]),
+        ColumnSpec::optional("purchase_price", &["平均取得単価"]),
+        ColumnSpec::required("realized_profit_and_loss", &["実現損益"]),
+    ];
+
+    const TRADE_HISTORY_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::required("trade_date", &["約定日"]),
+        ColumnSpec::optional("settlement_date", &["受渡日"]),
+        ColumnSpec::optional("security_code", &["銘柄コード"]),
+        ColumnSpec::required("security_name", &["銘柄名"]),
+        ColumnSpec::required("account", &["口座"]),
+        ColumnSpec::required("trade_type", &["売買区分"]),
+        ColumnSpec::required("shares", &["約定数量", "数量"]),
+        ColumnSpec::optional("unit_price", &["約定単価"]),
+        ColumnSpec::optional("fees", &["手数料"]),
+        ColumnSpec::required("settlement_amount", &["受渡金額"]),
+    ];
+
+    const HOLDINGS_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::optional("security_code", &["銘柄コード"]),
+        ColumnSpec::required("security_name", &["銘柄名"]),
+        ColumnSpec::required("account", &["口座"]),
+        ColumnSpec::required("shares", &["保有数量"]),
+        ColumnSpec::required("acquisition_price", &["平均取得単価"]),
+        ColumnSpec::required("current_price", &["現在値"]),
+        ColumnSpec::optional("valuation", &["評価額"]),
+        ColumnSpec::optional("unrealized_profit_and_loss", &["評価損益"]),
+    ];
+}
+
+impl BrokerAdapter for AuKabucom {
+    fn id(&self) -> &'static str {
+        "au-kabucom"
+    }
+
+    fn name(&self) -> &'static str {
+        "auカブコム証券"
+    }
+
+    fn dividend_columns(&self) -> &'static [ColumnSpec] {
+        Self::DIVIDEND_COLUMNS
+    }
+
+    fn profit_and_loss_columns(&self) -> &'static [ColumnSpec] {
+        Self::PROFIT_AND_LOSS_COLUMNS
+    }
+
+    fn trade_history_columns(&self) -> &'static [ColumnSpec] {
+        Self::TRADE_HISTORY_COLUMNS
+    }
+
+    fn holdings_columns(&self) -> &'static [ColumnSpec] {
+        Self::HOLDINGS_COLUMNS
+    }
+}
@@ -0,0 +1,112 @@
+use super::{au_kabucom::AuKabucom, matsui::Matsui, monex::Monex, rakuten::Rakuten, sbi::Sbi};
+use crate::services::{
+    account::AccountType,
+    csv::{column_map::ColumnSpec, lib::CSVRows},
+};
+use anyhow::{anyhow, Result};
+
+// 証券会社ごとのCSVレイアウトを、共通のDividendList/ProfitAndLoss/TradeHistory/Holdingに対応付ける
+pub trait BrokerAdapter: Sync {
+    fn id(&self) -> &'static str;
+    fn name(&self) -> &'static str;
+    fn dividend_columns(&self) -> &'static [ColumnSpec];
+    fn profit_and_loss_columns(&self) -> &'static [ColumnSpec];
+    fn trade_history_columns(&self) -> &'static [ColumnSpec];
+    fn holdings_columns(&self) -> &'static [ColumnSpec];
+
+    // 口座区分の列がないCSVの明細の口座区分。Noneなら判定できない口座として一般口座とみなす
+    fn default_account_type(&self) -> Option<AccountType> {
+        None
+    }
+}
+
+// どの証券会社の列とも一致しないCSVは、不足列を報告するため先頭の証券会社の形式で読む
+pub static BROKERS: [&dyn BrokerAdapter; 5] = [&Rakuten, &Sbi, &Monex, &Matsui, &AuKabucom];
+
+pub fn find_broker(id: &str) -> Result<&'static dyn BrokerAdapter> {
+    BROKERS
+        .iter()
+        .find(|broker| broker.id() == id)
+        .copied()
+        .ok_or_else(|| anyhow!("Unknown broker '{}'", id))
+}
+
+// 各証券会社の列定義でヘッダー行を探し、最も一致したものを選ぶ。
+// 複数の証券会社が同点で並んだ場合は推測せず、同点の証券会社をErrで返す
+pub fn detect_broker(
+    rows: &CSVRows,
+    columns: impl Fn(&dyn BrokerAdapter) -> &'static [ColumnSpec],
+) -> Result<&'static dyn BrokerAdapter, Vec<&'static dyn BrokerAdapter>> {
+    let scores = BROKERS
+        .iter()
+        .map(|&broker| (broker, rows.find_header_row(columns(broker)).1))
+        .collect::<Vec<_>>();
+    let best = scores
+        .iter()
+        .map(|(_, score)| *score)
+        .max()
+        .unwrap_or_default();
+    let candidates = scores
+        .into_iter()
+        .filter(|(_, score)| *score == best)
+        .map(|(broker, _)| broker)
+        .collect::<Vec<_>>();
+    match candidates[..] {
+        [broker] => Ok(broker),
+        _ if best.matched == 0 => Ok(BROKERS[0]),
+        _ => Err(candidates),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::csv::lib::CSVAccessor;
+
+    fn detect_profit_and_loss(header: &str) -> Result<&'static str, Vec<&'static str>> {
+        let rows = CSVAccessor::read(format!("{header}\n").into_bytes(), Some("utf-8")).unwrap();
+        detect_broker(&rows, |broker| broker.profit_and_loss_columns())
+            .map(|broker| broker.id())
+            .map_err(|candidates| candidates.iter().map(|broker| broker.id()).collect())
+    }
+
+    #[test]
+    fn detects_rakuten_header() {
+        let header = "約定日,受渡日,銘柄コード,銘柄名,口座,信用区分,取引,数量[株],売却/決済単価[円],売却/決済額[円],平均取得価額[円],実現損益[円]";
+        assert_eq!(detect_profit_and_loss(header), Ok("rakuten"));
+    }
+
+    #[test]
+    fn detects_sbi_header() {
+        let header = "銘柄コード,銘柄,約定日,数量,取引,受渡日,売却/決済金額,費用,取得/新規年月日,取得/新規金額,損益金額/徴収額";
+        assert_eq!(detect_profit_and_loss(header), Ok("sbi"));
+    }
+
+    #[test]
+    fn detects_monex_header() {
+        let header =
+            "約定日,受渡日,銘柄コード,銘柄名,預り区分,数量,売却単価,売却金額,取得単価,損益";
+        assert_eq!(detect_profit_and_loss(header), Ok("monex"));
+    }
+
+    #[test]
+    fn detects_matsui_header() {
+        let header =
+            "約定日,受渡日,銘柄コード,銘柄名,口座区分,数量,約定単価,受渡金額,取得単価,譲渡損益";
+        assert_eq!(detect_profit_and_loss(header), Ok("matsui"));
+    }
+
+    #[test]
+    fn detects_au_kabucom_header() {
+        let header =
+            "約定日,受渡日,銘柄コード,銘柄名,口座,数量,約定単価,売却代金,平均取得単価,実現損益";
+        assert_eq!(detect_profit_and_loss(header), Ok("au-kabucom"));
+    }
+
+    #[test]
+    fn tied_header_returns_candidates_instead_of_guessing() {
+        // 預り区分と口座区分の両方がある列だけのCSVは、マネックス証券・松井証券のどちらとも決められない
+        let header = "約定日,銘柄コード,銘柄名,預り区分,口座区分,数量,損益,譲渡損益";
+        assert_eq!(detect_profit_and_loss(header), Err(vec!["monex", "matsui"]));
+    }
+}
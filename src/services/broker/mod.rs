@@ -0,0 +1,6 @@
+pub mod au_kabucom;
+pub mod lib;
+pub mod matsui;
+pub mod monex;
+pub mod rakuten;
+pub mod sbi;
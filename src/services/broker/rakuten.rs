@@ -0,0 +1,92 @@
+use super::lib::BrokerAdapter;
+use crate::services::csv::column_map::ColumnSpec;
+
+pub struct Rakuten;
+
+impl Rakuten {
+    const DIVIDEND_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::required("settlement_date", &["入金日", "受渡日"]),
+        ColumnSpec::optional("payment_date", &["支払確定日", "現地支払日"]),
+        ColumnSpec::optional("product", &["商品"]),
+        ColumnSpec::required("account", &["口座"]),
+        ColumnSpec::required("security_code", &["銘柄コード", "ティッカー"]),
+        ColumnSpec::required("security_name", &["銘柄", "銘柄名"]),
+        ColumnSpec::optional("currency", &["受取通貨"]),
+        ColumnSpec::optional("unit_price", &["単価"]),
+        ColumnSpec::required("shares", &["数量"]),
+        ColumnSpec::required("dividends_before_tax", &["配当・分配金(税引前)"]),
+        ColumnSpec::required("taxes", &["税額"]),
+        ColumnSpec::optional(
+            "foreign_taxes",
+            &["外国源泉徴収税額", "外国源泉税額", "外国源泉税", "外国税額"],
+        ),
+        ColumnSpec::optional(
+            "domestic_taxes",
+            &["国内源泉徴収税額", "国内源泉税額", "国内源泉税", "国内税額"],
+        ),
+        ColumnSpec::required("net_amount_received", &["受取金額"]),
+    ];
+
+    const PROFIT_AND_LOSS_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::required("trade_date", &["約定日"]),
+        ColumnSpec::optional("settlement_date", &["受渡日"]),
+        ColumnSpec::required("security_code", &["銘柄コード", "ティッカー"]),
+        ColumnSpec::required("security_name", &["銘柄名", "銘柄"]),
+        ColumnSpec::required("account", &["口座"]),
+        ColumnSpec::required("shares", &["数量"]),
+        ColumnSpec::optional("asked_price", &["売却/決済単価"]),
+        ColumnSpec::optional("proceeds", &["売却/決済額"]),
+        ColumnSpec::optional("purchase_price", &["平均取得価額"]),
+        ColumnSpec::required("realized_profit_and_loss", &["実現損益"]),
+    ];
+
+    const TRADE_HISTORY_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::required("trade_date", &["約定日"]),
+        ColumnSpec::optional("settlement_date", &["受渡日"]),
+        ColumnSpec::optional("security_code", &["銘柄コード", "ティッカー"]),
+        ColumnSpec::required("security_name", &["銘柄名", "ファンド名", "銘柄"]),
+        ColumnSpec::required("account", &["口座区分", "口座"]),
+        ColumnSpec::required("trade_type", &["売買区分", "取引"]),
+        ColumnSpec::required("shares", &["数量"]),
+        ColumnSpec::optional("unit_price", &["単価"]),
+        ColumnSpec::optional("fees", &["手数料"]),
+        ColumnSpec::required("settlement_amount", &["受渡金額"]),
+    ];
+
+    const HOLDINGS_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::optional("security_code", &["銘柄コード・ティッカー", "銘柄コード"]),
+        ColumnSpec::required("security_name", &["銘柄", "銘柄名", "ファンド名"]),
+        ColumnSpec::required("account", &["口座", "口座区分"]),
+        ColumnSpec::required("shares", &["保有数量"]),
+        ColumnSpec::required("acquisition_price", &["平均取得価額"]),
+        ColumnSpec::required("current_price", &["現在値", "基準価額"]),
+        ColumnSpec::optional("valuation", &["時価評価額"]),
+        ColumnSpec::optional("unrealized_profit_and_loss", &["評価損益"]),
+    ];
+}
+
+impl BrokerAdapter for Rakuten {
+    fn id(&self) -> &'static str {
+        "rakuten"
+    }
+
+    fn name(&self) -> &'static str {
+        "楽天証券"
+    }
+
+    fn dividend_columns(&self) -> &'static [ColumnSpec] {
+        Self::DIVIDEND_COLUMNS
+    }
+
+    fn profit_and_loss_columns(&self) -> &'static [ColumnSpec] {
+        Self::PROFIT_AND_LOSS_COLUMNS
+    }
+
+    fn trade_history_columns(&self) -> &'static [ColumnSpec] {
+        Self::TRADE_HISTORY_COLUMNS
+    }
+
+    fn holdings_columns(&self) -> &'static [ColumnSpec] {
+        Self::HOLDINGS_COLUMNS
+    }
+}
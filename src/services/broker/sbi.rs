@@ -0,0 +1,96 @@
+use super::lib::BrokerAdapter;
+use crate::services::{account::AccountType, csv::column_map::ColumnSpec};
+
+// 譲渡益税明細は口座区分を行ごとに持たないため、口座は任意列とする。
+// 譲渡益税明細は特定口座の明細なので、口座区分の列がなければ特定口座とみなす
+pub struct Sbi;
+
+impl Sbi {
+    const DIVIDEND_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::required("settlement_date", &["受渡日", "入金日"]),
+        ColumnSpec::optional("payment_date", &["支払確定日", "現地支払日"]),
+        ColumnSpec::optional("product", &["商品", "商品区分"]),
+        ColumnSpec::optional("account", &["口座区分", "預り区分"]),
+        ColumnSpec::required("security_code", &["銘柄コード"]),
+        ColumnSpec::required("security_name", &["銘柄名", "銘柄"]),
+        ColumnSpec::optional("currency", &["通貨"]),
+        ColumnSpec::optional("unit_price", &["1株当たり配当金"]),
+        ColumnSpec::required("shares", &["数量", "保有数量"]),
+        ColumnSpec::required("dividends_before_tax", &["配当金額(税引前)", "支払金額"]),
+        ColumnSpec::required("taxes", &["源泉徴収税額", "税額"]),
+        ColumnSpec::optional(
+            "foreign_taxes",
+            &["外国源泉徴収税額", "外国源泉税額", "外国源泉税", "外国税額"],
+        ),
+        ColumnSpec::optional(
+            "domestic_taxes",
+            &["国内源泉徴収税額", "国内源泉税額", "国内源泉税", "国内税額"],
+        ),
+        ColumnSpec::required("net_amount_received", &["受取額", "受取金額"]),
+    ];
+
+    const PROFIT_AND_LOSS_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::required("trade_date", &["約定日"]),
+        ColumnSpec::optional("settlement_date", &["受渡日"]),
+        ColumnSpec::required("security_code", &["銘柄コード"]),
+        ColumnSpec::required("security_name", &["銘柄", "銘柄名"]),
+        ColumnSpec::optional("account", &["口座区分", "預り区分"]),
+        ColumnSpec::required("shares", &["数量"]),
+        ColumnSpec::optional("proceeds", &["売却/決済金額"]),
+        ColumnSpec::required("realized_profit_and_loss", &["損益金額/徴収額", "損益金額"]),
+    ];
+
+    const TRADE_HISTORY_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::required("trade_date", &["約定日"]),
+        ColumnSpec::optional("settlement_date", &["受渡日"]),
+        ColumnSpec::optional("security_code", &["銘柄コード"]),
+        ColumnSpec::required("security_name", &["銘柄", "銘柄名", "ファンド名"]),
+        ColumnSpec::required("account", &["預り", "預り区分", "口座区分"]),
+        ColumnSpec::required("trade_type", &["取引"]),
+        ColumnSpec::required("shares", &["約定数量", "数量"]),
+        ColumnSpec::optional("unit_price", &["約定単価"]),
+        ColumnSpec::optional("fees", &["手数料/諸経費等"]),
+        ColumnSpec::required("settlement_amount", &["受渡金額/決済損益", "受渡金額"]),
+    ];
+
+    const HOLDINGS_COLUMNS: &'static [ColumnSpec] = &[
+        ColumnSpec::optional("security_code", &["銘柄コード"]),
+        ColumnSpec::required("security_name", &["銘柄名称", "銘柄名", "ファンド名"]),
+        ColumnSpec::optional("account", &["預り区分", "口座区分"]),
+        ColumnSpec::required("shares", &["数量", "保有数量"]),
+        ColumnSpec::required("acquisition_price", &["取得単価"]),
+        ColumnSpec::required("current_price", &["現在値", "基準価額"]),
+        ColumnSpec::optional("valuation", &["評価額"]),
+        ColumnSpec::optional("unrealized_profit_and_loss", &["損益"]),
+    ];
+}
+
+impl BrokerAdapter for Sbi {
+    fn id(&self) -> &'static str {
+        "sbi"
+    }
+
+    fn name(&self) -> &'static str {
+        "SBI証券"
+    }
+
+    fn dividend_columns(&self) -> &'static [ColumnSpec] {
+        Self::DIVIDEND_COLUMNS
+    }
+
+    fn profit_and_loss_columns(&self) -> &'static [ColumnSpec] {
+        Self::PROFIT_AND_LOSS_COLUMNS
+    }
+
+    fn trade_history_columns(&self) -> &'static [ColumnSpec] {
+        Self::TRADE_HISTORY_COLUMNS
+    }
+
+    fn holdings_columns(&self) -> &'static [ColumnSpec] {
+        Self::HOLDINGS_COLUMNS
+    }
+
+    fn default_account_type(&self) -> Option<AccountType> {
+        Some(AccountType::Specific)
+    }
+}
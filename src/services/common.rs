@@ -1,53 +1,215 @@
-use anyhow::anyhow;
+use anyhow::{anyhow, Result};
 use chrono::NaiveDate;
+use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::str::FromStr;
 
-pub fn parse_date(date_str: Option<&str>) -> Option<NaiveDate> {
-    match date_str {
-        Some(date_str) => {
-            let date = NaiveDate::parse_from_str(&date_str, "%Y/%m/%d")
-                .map_err(|e| anyhow!("Failed to parse date '{}': {}", date_str, e));
-
-            match date {
-                Ok(date) => Some(date),
-                Err(e) => {
-                    println!("{e}");
-                    None
-                }
-            }
+use super::{
+    csv::column_map::ColumnMap,
+    money::{Currency, Money},
+};
+
+// 読み込み時に値を解釈できなかったセルの情報
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseDiagnostic {
+    pub line: usize,    // CSVファイル上の行番号
+    pub column: String, // ヘッダー名
+    pub value: String,  // 元の値
+    pub reason: String,
+}
+
+impl ParseDiagnostic {
+    pub fn new(record: &StringRecord, column: String, value: &str, reason: String) -> Self {
+        ParseDiagnostic {
+            line: record
+                .position()
+                .map_or(0, |position| position.line() as usize),
+            column,
+            value: value.to_string(),
+            reason,
+        }
+    }
+
+    // 必須の値がないため行を読み飛ばしたことを記録する。値を解釈できなかったセルは読み込み時に
+    // 記録済みのため、同じセルの診断情報に読み飛ばしたことを書き加えて1件にまとめる
+    pub fn push_row_skipped(
+        diagnostics: &mut Vec<ParseDiagnostic>,
+        record: &StringRecord,
+        columns: &ColumnMap,
+        field: &str,
+        reason: &str,
+    ) {
+        let diagnostic = ParseDiagnostic::new(
+            record,
+            columns.header(field),
+            columns.get(record, field).unwrap_or(""),
+            reason.to_string(),
+        );
+        let existing = diagnostics.iter_mut().rev().find(|existing| {
+            existing.line == diagnostic.line && existing.column == diagnostic.column
+        });
+        match existing {
+            Some(existing) => existing.reason = format!("Row skipped: {}", existing.reason),
+            None => diagnostics.push(diagnostic),
         }
-        None => None,
     }
 }
 
-pub fn parse_int(num_str: Option<&str>) -> Option<i32> {
-    match num_str {
-        Some(s) => match s.replace(",", "").parse::<i32>() {
-            Ok(n) => Some(n),
+// 1行分のレコードを列名で読み出し、解釈できなかった値を診断情報として記録する
+pub struct RecordReader<'a> {
+    record: &'a StringRecord,
+    columns: &'a ColumnMap,
+    diagnostics: &'a mut Vec<ParseDiagnostic>,
+}
+
+impl<'a> RecordReader<'a> {
+    pub fn new(
+        record: &'a StringRecord,
+        columns: &'a ColumnMap,
+        diagnostics: &'a mut Vec<ParseDiagnostic>,
+    ) -> Self {
+        RecordReader {
+            record,
+            columns,
+            diagnostics,
+        }
+    }
+
+    pub fn string(&mut self, field: &str) -> Option<String> {
+        parse_string(self.columns.get(self.record, field))
+    }
+
+    pub fn date(&mut self, field: &str) -> Option<NaiveDate> {
+        self.parse(field, parse_date)
+    }
+
+    pub fn int(&mut self, field: &str) -> Option<i32> {
+        self.parse(field, parse_int)
+    }
+
+    pub fn decimal(&mut self, field: &str) -> Option<Decimal> {
+        self.parse(field, parse_decimal)
+    }
+
+    pub fn money(&mut self, field: &str, currency: &Currency) -> Option<Money> {
+        self.parse(field, parse_decimal)
+            .map(|amount| Money::new(amount, currency.clone()))
+    }
+
+    fn parse<T>(
+        &mut self,
+        field: &str,
+        parser: fn(Option<&str>) -> Result<Option<T>>,
+    ) -> Option<T> {
+        let value = self.columns.get(self.record, field);
+        match parser(value) {
+            Ok(value) => value,
             Err(e) => {
-                println!("Failed to parse integer '{}': {}", s, e);
+                self.diagnostics.push(ParseDiagnostic::new(
+                    self.record,
+                    self.columns.header(field),
+                    value.unwrap_or(""),
+                    e.to_string(),
+                ));
                 None
             }
-        },
-        None => None,
+        }
     }
 }
 
-pub fn parse_float(num_str: Option<&str>) -> Option<f64> {
-    match num_str {
-        Some(s) => match s.replace(",", "").parse::<f64>() {
-            Ok(n) => Some(n),
-            Err(e) => {
-                println!("Failed to parse float '{}': {}", s, e);
-                None
-            }
-        },
-        None => None,
+// 空欄や「-」は値なしとして扱う
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty() && *s != "-")
+}
+
+pub fn parse_date(date_str: Option<&str>) -> Result<Option<NaiveDate>> {
+    match non_empty(date_str) {
+        Some(date_str) => {
+            // 証券会社によって日付の書式が異なる
+            ["%Y/%m/%d", "%Y-%m-%d", "%Y%m%d", "%Y年%m月%d日"]
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(date_str, format).ok())
+                .map(Some)
+                .ok_or_else(|| anyhow!("Failed to parse date '{}'", date_str))
+        }
+        None => Ok(None),
+    }
+}
+
+pub fn parse_int(num_str: Option<&str>) -> Result<Option<i32>> {
+    match non_empty(num_str) {
+        Some(s) => s
+            .replace(",", "")
+            .parse::<i32>()
+            .map(Some)
+            .map_err(|e| anyhow!("Failed to parse integer '{}': {}", s, e)),
+        None => Ok(None),
+    }
+}
+
+pub fn parse_decimal(num_str: Option<&str>) -> Result<Option<Decimal>> {
+    match non_empty(num_str) {
+        Some(s) => Decimal::from_str(&s.replace(",", ""))
+            .map(Some)
+            .map_err(|e| anyhow!("Failed to parse decimal '{}': {}", s, e)),
+        None => Ok(None),
     }
 }
 
 pub fn parse_string(value: Option<&str>) -> Option<String> {
-    match value {
-        Some(s) => Some(s.to_string()),
-        None => None,
+    value.map(|s| s.to_string())
+}
+
+// 基準額に対する割合（%、小数第2位まで）
+pub fn rate(amount: Option<Decimal>, base: Option<Decimal>) -> Option<Decimal> {
+    let (amount, base) = (amount?, base?);
+    if base.is_zero() {
+        return None;
+    }
+    Some((amount / base * Decimal::ONE_HUNDRED).round_dp(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::csv::column_map::ColumnSpec;
+
+    const SPECS: &[ColumnSpec] = &[
+        ColumnSpec::required("trade_date", &["約定日"]),
+        ColumnSpec::optional("shares", &["数量"]),
+    ];
+
+    fn skip_row(cells: Vec<&str>) -> Vec<ParseDiagnostic> {
+        let columns = ColumnMap::new(SPECS, &StringRecord::from(vec!["約定日", "数量"])).unwrap();
+        let record = StringRecord::from(cells);
+        let mut diagnostics = Vec::new();
+        let mut reader = RecordReader::new(&record, &columns, &mut diagnostics);
+        assert_eq!(reader.date("trade_date"), None);
+        ParseDiagnostic::push_row_skipped(
+            &mut diagnostics,
+            &record,
+            &columns,
+            "trade_date",
+            "Row skipped: missing trade date",
+        );
+        diagnostics
+    }
+
+    #[test]
+    fn unparsable_cell_is_reported_once() {
+        let diagnostics = skip_row(vec!["2025/13/01", "100"]);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].reason.starts_with("Row skipped: "));
+        assert_ne!(diagnostics[0].reason, "Row skipped: missing trade date");
+    }
+
+    #[test]
+    fn empty_cell_is_reported_as_missing() {
+        let diagnostics = skip_row(vec!["", "100"]);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].reason, "Row skipped: missing trade date");
     }
 }
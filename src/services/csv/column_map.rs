@@ -0,0 +1,180 @@
+use anyhow::{anyhow, Result};
+use csv::StringRecord;
+use std::collections::HashMap;
+
+// CSVの列とモデルのフィールドとの対応定義
+pub struct ColumnSpec {
+    pub field: &'static str,              // モデルのフィールド名
+    pub headers: &'static [&'static str], // 対応するヘッダー名（単位の[...]を除いたもの）
+    pub required: bool,
+}
+
+impl ColumnSpec {
+    pub const fn required(field: &'static str, headers: &'static [&'static str]) -> Self {
+        ColumnSpec {
+            field,
+            headers,
+            required: true,
+        }
+    }
+
+    pub const fn optional(field: &'static str, headers: &'static [&'static str]) -> Self {
+        ColumnSpec {
+            field,
+            headers,
+            required: false,
+        }
+    }
+}
+
+// ヘッダー行の候補としての一致度。必須列が揃っていることを優先し、次に一致した列数で比較する
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HeaderScore {
+    pub has_required: bool,
+    pub matched: usize,
+}
+
+pub struct ColumnMap {
+    indices: HashMap<&'static str, usize>,
+    defaults: HashMap<&'static str, &'static str>, // CSVに列がないフィールドの値
+    headers: Vec<String>,
+    pub ignored: Vec<String>, // どのフィールドにも対応しなかったヘッダー
+}
+
+impl ColumnMap {
+    pub fn new(specs: &[ColumnSpec], headers: &StringRecord) -> Result<Self> {
+        let normalized = Self::normalize_headers(headers);
+
+        let mut indices = HashMap::new();
+        let mut missing = Vec::new();
+        for spec in specs {
+            match Self::find_index(spec, &normalized) {
+                Some(index) => {
+                    indices.insert(spec.field, index);
+                }
+                None if spec.required => missing.push(spec.headers[0]),
+                None => {}
+            }
+        }
+
+        let ignored = headers
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !indices.values().any(|index| index == i))
+            .map(|(_, header)| header.to_string())
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "Missing required columns: {} (ignored columns: {})",
+                missing.join(", "),
+                ignored.join(", ")
+            ));
+        }
+
+        Ok(ColumnMap {
+            indices,
+            defaults: HashMap::new(),
+            headers: headers.iter().map(|header| header.to_string()).collect(),
+            ignored,
+        })
+    }
+
+    pub fn score(specs: &[ColumnSpec], headers: &StringRecord) -> HeaderScore {
+        let normalized = Self::normalize_headers(headers);
+        let mut score = HeaderScore {
+            has_required: true,
+            matched: 0,
+        };
+        for spec in specs {
+            match Self::find_index(spec, &normalized) {
+                Some(_) => score.matched += 1,
+                None if spec.required => score.has_required = false,
+                None => {}
+            }
+        }
+        score
+    }
+
+    // CSVに列がないときに使う値を設定する。列があれば、空欄でもCSVの値を使う
+    pub fn with_default(mut self, field: &'static str, value: &'static str) -> Self {
+        if !self.indices.contains_key(field) {
+            self.defaults.insert(field, value);
+        }
+        self
+    }
+
+    pub fn get<'a>(&self, record: &'a StringRecord, field: &str) -> Option<&'a str> {
+        match self.indices.get(field) {
+            Some(&index) => record.get(index),
+            None => self.defaults.get(field).copied(),
+        }
+    }
+
+    // フィールドに対応するCSV上のヘッダー名（診断メッセージ用）
+    pub fn header(&self, field: &str) -> String {
+        self.indices
+            .get(field)
+            .and_then(|&index| self.headers.get(index))
+            .cloned()
+            .unwrap_or_else(|| field.to_string())
+    }
+
+    fn find_index(spec: &ColumnSpec, normalized: &[String]) -> Option<usize> {
+        normalized.iter().position(|header| {
+            spec.headers
+                .iter()
+                .any(|name| Self::normalize_header(name) == *header)
+        })
+    }
+
+    fn normalize_headers(headers: &StringRecord) -> Vec<String> {
+        headers.iter().map(Self::normalize_header).collect()
+    }
+
+    // 全角括弧・空白の揺れを吸収し、末尾の単位表記（[円] など）を取り除く
+    fn normalize_header(header: &str) -> String {
+        let header = header
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match c {
+                '（' => '(',
+                '）' => ')',
+                '［' => '[',
+                '］' => ']',
+                '／' => '/',
+                _ => c,
+            })
+            .collect::<String>();
+
+        match header.find('[') {
+            Some(index) => header[..index].to_string(),
+            None => header,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPECS: &[ColumnSpec] = &[
+        ColumnSpec::required("trade_date", &["約定日"]),
+        ColumnSpec::optional("account", &["口座区分"]),
+    ];
+
+    #[test]
+    fn default_is_used_only_when_column_is_missing() {
+        let record = StringRecord::from(vec!["2025/01/06", ""]);
+
+        let without_account = ColumnMap::new(SPECS, &StringRecord::from(vec!["約定日"]))
+            .unwrap()
+            .with_default("account", "特定");
+        assert_eq!(without_account.get(&record, "account"), Some("特定"));
+
+        let with_account = ColumnMap::new(SPECS, &StringRecord::from(vec!["約定日", "口座区分"]))
+            .unwrap()
+            .with_default("account", "特定");
+        assert_eq!(with_account.get(&record, "account"), Some(""));
+    }
+}
@@ -1,24 +1,150 @@
 use anyhow::{anyhow, Result};
+use chardetng::EncodingDetector;
 use csv::StringRecord;
-use encoding_rs::SHIFT_JIS;
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_8};
+use serde::Serialize;
+
+use super::column_map::{ColumnMap, ColumnSpec, HeaderScore};
+
+// ヘッダー行より前にある「期間」「出力日時」などの付帯情報
+#[derive(Debug, Clone, Serialize)]
+pub struct PreambleEntry {
+    pub key: String,
+    pub value: String,
+}
+
+// ヘッダー行を特定する前の、デコード済みの全行
+pub struct CSVRows {
+    pub encoding: String, // 読み込みに使用した文字コード
+    pub rows: Vec<StringRecord>,
+}
+
+pub struct CSVData {
+    pub encoding: String, // 読み込みに使用した文字コード
+    pub preamble: Vec<PreambleEntry>,
+    pub headers: StringRecord,
+    pub records: Vec<StringRecord>,
+}
 
 pub struct CSVAccessor;
 
 impl CSVAccessor {
-    pub fn read(bytes: Vec<u8>) -> Result<Vec<StringRecord>> {
-        let (cow, _, had_errors) = SHIFT_JIS.decode(&bytes);
+    pub fn read(bytes: Vec<u8>, encoding: Option<&str>) -> Result<CSVRows> {
+        let (encoding, encoding_name) = match encoding {
+            Some(label) => {
+                let encoding = Self::encoding_for_label(label)?;
+                (encoding, encoding.name().to_string())
+            }
+            None => Self::detect_encoding(&bytes),
+        };
+
+        let (cow, had_errors) = encoding.decode_with_bom_removal(&bytes);
         if had_errors {
-            return Err(anyhow!("Error decoding Shift-JIS"));
+            return Err(anyhow!("Error decoding {}", encoding_name));
         }
         let utf8_string = cow.into_owned();
         let mut rdr = csv::ReaderBuilder::new()
-            .has_headers(true)
+            .has_headers(false)
+            .flexible(true)
             .from_reader(utf8_string.as_bytes());
 
-        let mut result = Vec::new();
+        let mut rows = Vec::new();
         for record in rdr.records() {
-            result.push(record?);
+            rows.push(record?);
+        }
+
+        Ok(CSVRows {
+            encoding: encoding_name,
+            rows,
+        })
+    }
+
+    // BOMを優先し、なければchardetngで推定する
+    fn detect_encoding(bytes: &[u8]) -> (&'static Encoding, String) {
+        if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+            return (encoding, format!("{} (BOM)", encoding.name()));
+        }
+
+        let mut detector = EncodingDetector::new();
+        detector.feed(bytes, true);
+        let encoding = detector.guess(Some(b"jp"), true);
+        (encoding, encoding.name().to_string())
+    }
+
+    // encoding_rsのShift_JISはWindows-31J(CP932)相当のため、CP932系のラベルも同じ扱いにする
+    fn encoding_for_label(label: &str) -> Result<&'static Encoding> {
+        match label.to_ascii_lowercase().as_str() {
+            "cp932" | "ms932" | "windows-31j" | "sjis" | "shift_jis" | "shift-jis" => Ok(SHIFT_JIS),
+            "utf8" | "utf-8" | "utf-8-bom" => Ok(UTF_8),
+            _ => Encoding::for_label(label.as_bytes())
+                .ok_or_else(|| anyhow!("Unknown encoding '{}'", label)),
+        }
+    }
+}
+
+impl CSVRows {
+    // 必須列がすべて揃う最初の行をヘッダーとみなす。見つからなければ最も一致した行を返し、
+    // 不足列のエラーはColumnMap側で報告する
+    pub fn find_header_row(&self, specs: &[ColumnSpec]) -> (usize, HeaderScore) {
+        let mut best = (0, HeaderScore::default());
+        for (index, row) in self.rows.iter().enumerate() {
+            let score = ColumnMap::score(specs, row);
+            if score.has_required {
+                return (index, score);
+            }
+            if score.matched > best.1.matched {
+                best = (index, score);
+            }
+        }
+        best
+    }
+
+    pub fn into_data(self, specs: &[ColumnSpec]) -> CSVData {
+        let (header_index, _) = self.find_header_row(specs);
+        let preamble = self.rows[..header_index]
+            .iter()
+            .filter_map(Self::parse_preamble_row)
+            .collect();
+        let headers = self.rows.get(header_index).cloned().unwrap_or_default();
+        let records = self
+            .rows
+            .into_iter()
+            .skip(header_index + 1)
+            .filter(|record| record.iter().any(|field| !field.trim().is_empty()))
+            .collect();
+
+        CSVData {
+            encoding: self.encoding,
+            preamble,
+            headers,
+            records,
+        }
+    }
+
+    // 「期間,2024/01/01～2024/12/31」「口座：総合」のような行をキーと値に分ける
+    fn parse_preamble_row(row: &StringRecord) -> Option<PreambleEntry> {
+        let fields = row
+            .iter()
+            .map(|field| field.trim())
+            .filter(|field| !field.is_empty())
+            .collect::<Vec<_>>();
+
+        match fields.as_slice() {
+            [] => None,
+            [field] => {
+                let (key, value) = field
+                    .split_once('：')
+                    .or_else(|| field.split_once(':'))
+                    .unwrap_or((field, ""));
+                Some(PreambleEntry {
+                    key: key.trim().to_string(),
+                    value: value.trim().to_string(),
+                })
+            }
+            [key, values @ ..] => Some(PreambleEntry {
+                key: key.trim_end_matches(['：', ':']).to_string(),
+                value: values.join(" "),
+            }),
         }
-        Ok(result)
     }
 }
@@ -1 +1,2 @@
+pub mod column_map;
 pub mod lib;
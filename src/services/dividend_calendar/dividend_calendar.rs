@@ -0,0 +1,210 @@
+use chrono::{Datelike, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::services::{
+    dividend_list::dividend_list::DividendList,
+    dividend_summary::dividend_summary::security_key,
+    money::{Currency, Money},
+};
+
+// 予定を出す期間（月数）
+pub const PROJECTION_MONTHS: i32 = 12;
+
+// 推定できる支払い間隔（月数）。差が同じなら間隔の長い方を選ぶ
+const PAYOUT_INTERVALS: [i32; 4] = [12, 6, 3, 1];
+
+// 過去の入金から推定した、今後の配当の入金予定
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectedDividend {
+    pub payment_month: Option<NaiveDate>, // 入金予定月（月初日）
+    pub security_code: Option<String>,    // 銘柄コード
+    pub security_name: Option<String>,    // 銘柄名
+    pub currency: Option<String>,         // 受取通貨
+    pub payments_per_year: Option<i32>,   // 年間支払回数
+    pub last_settlement_date: Option<NaiveDate>, // 前回の入金日
+    pub dividend_per_share: Option<Money>, // 前回の1株あたり配当（税引前）
+    pub shares: Option<Decimal>,          // 現在の保有数量
+    pub projected_dividend: Option<Money>, // 予想配当（税引前）
+}
+
+// 同じ月の入金（口座違いなど）をまとめたもの
+#[derive(Debug, Default)]
+struct MonthlyPayment {
+    security_code: Option<String>,
+    security_name: Option<String>,
+    settlement_date: Option<NaiveDate>,
+    before_tax: Decimal,
+    shares: Decimal,
+}
+
+impl ProjectedDividend {
+    pub fn new() -> Self {
+        ProjectedDividend {
+            payment_month: None,
+            security_code: None,
+            security_name: None,
+            currency: None,
+            payments_per_year: None,
+            last_settlement_date: None,
+            dividend_per_share: None,
+            shares: None,
+            projected_dividend: None,
+        }
+    }
+
+    // 銘柄・通貨ごとに、直近2回の入金月の間隔から支払い間隔を推定し（数量の分からない入金は使わない）、
+    // 前回の1株あたり配当 × 現在の保有数量を、前回の入金月から間隔ごとの月に割り当てる。
+    // 現在の保有数量が分からない銘柄は前回の入金時の数量を使い、保有証券一覧がすべての保有を表す場合は
+    // 一覧にない銘柄を売却済みとして除く。過去1年間に入金のない銘柄は減配・売却の可能性があるため除く
+    pub fn project(
+        dividend_list: &[DividendList],
+        current_shares: &HashMap<String, Decimal>,
+        is_complete: bool,
+        from: NaiveDate,
+    ) -> Vec<Self> {
+        let mut groups: BTreeMap<(String, Currency), BTreeMap<i32, MonthlyPayment>> =
+            BTreeMap::new();
+        for dividend in dividend_list {
+            let (Some(settlement_date), Some(before_tax), Some(shares)) = (
+                dividend.settlement_date,
+                &dividend.dividends_before_tax,
+                dividend.shares.filter(|shares| *shares > 0),
+            ) else {
+                continue;
+            };
+            let key = security_key(
+                dividend.security_code.as_deref(),
+                dividend.security_name.as_deref(),
+            );
+            let payment = groups
+                .entry((key, before_tax.currency.clone()))
+                .or_default()
+                .entry(month_index(settlement_date))
+                .or_default();
+            payment.security_code = payment
+                .security_code
+                .take()
+                .or_else(|| dividend.security_code.clone());
+            payment.security_name = payment
+                .security_name
+                .take()
+                .or_else(|| dividend.security_name.clone());
+            payment.settlement_date = payment.settlement_date.max(Some(settlement_date));
+            payment.before_tax += before_tax.amount;
+            payment.shares += Decimal::from(shares);
+        }
+
+        let from = month_index(from);
+        let mut projections = Vec::new();
+        for ((key, currency), payments) in groups {
+            let mut months = payments.keys().rev();
+            let Some(&last_month) = months.next() else {
+                continue;
+            };
+            if from - last_month > PROJECTION_MONTHS {
+                continue;
+            }
+            let interval = months.next().map_or(PROJECTION_MONTHS, |previous| {
+                payout_interval(last_month - previous)
+            });
+
+            let last = &payments[&last_month];
+            let per_share = last.before_tax / last.shares;
+            let shares = match current_shares.get(&key) {
+                Some(shares) => *shares,
+                None if is_complete => continue,
+                None => last.shares,
+            };
+            if shares.is_zero() {
+                continue;
+            }
+
+            let money = |amount: Decimal| Money::new(amount, currency.clone());
+            let mut month = last_month + interval;
+            while month < from + PROJECTION_MONTHS {
+                if month >= from {
+                    projections.push(ProjectedDividend {
+                        payment_month: month_start(month),
+                        security_code: last.security_code.clone(),
+                        security_name: last.security_name.clone(),
+                        currency: Some(currency.to_string()),
+                        payments_per_year: Some(PROJECTION_MONTHS / interval),
+                        last_settlement_date: last.settlement_date,
+                        dividend_per_share: Some(money(per_share.round_dp(2))),
+                        shares: Some(shares),
+                        projected_dividend: Some(money((per_share * shares).round_dp(2))),
+                    });
+                }
+                month += interval;
+            }
+        }
+
+        projections.sort_by(|a, b| {
+            (a.payment_month, &a.security_code).cmp(&(b.payment_month, &b.security_code))
+        });
+        projections
+    }
+
+    // 入金予定月ごと・期間全体の通貨ごとの合計。月の合計は入金予定月を、全体の合計は銘柄名欄に名前を入れる
+    pub fn new_total(payment_month: Option<NaiveDate>, label: Option<&str>, total: Money) -> Self {
+        ProjectedDividend {
+            payment_month,
+            security_name: label.map(|label| label.to_string()),
+            currency: Some(total.currency.to_string()),
+            projected_dividend: Some(total),
+            ..Self::new()
+        }
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        let money = |m: &Option<Money>| m.as_ref().map(|m| m.to_string());
+        vec![
+            (
+                "payment_month".to_string(),
+                self.payment_month.map(|d| d.format("%Y/%m").to_string()),
+            ),
+            ("security_code".to_string(), self.security_code.clone()),
+            ("security_name".to_string(), self.security_name.clone()),
+            ("currency".to_string(), self.currency.clone()),
+            (
+                "payments_per_year".to_string(),
+                self.payments_per_year.map(|p| p.to_string()),
+            ),
+            (
+                "last_settlement_date".to_string(),
+                self.last_settlement_date.map(|d| d.to_string()),
+            ),
+            (
+                "dividend_per_share".to_string(),
+                money(&self.dividend_per_share),
+            ),
+            (
+                "shares".to_string(),
+                self.shares.map(|s| s.normalize().to_string()),
+            ),
+            (
+                "projected_dividend".to_string(),
+                money(&self.projected_dividend),
+            ),
+        ]
+    }
+}
+
+// 入金月の差を、最も近い支払い間隔に丸める
+fn payout_interval(months: i32) -> i32 {
+    PAYOUT_INTERVALS
+        .into_iter()
+        .min_by_key(|interval| (interval - months).abs())
+        .unwrap_or(PROJECTION_MONTHS)
+}
+
+// 月の通し番号（年 × 12 + 月 - 1）
+pub fn month_index(date: NaiveDate) -> i32 {
+    date.year() * 12 + date.month0() as i32
+}
+
+pub fn month_start(index: i32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(index.div_euclid(12), index.rem_euclid(12) as u32 + 1, 1)
+}
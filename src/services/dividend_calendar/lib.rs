@@ -0,0 +1,282 @@
+use super::{
+    super::dividend_list::dividend_list::DividendList,
+    super::dividend_summary::dividend_summary::security_key,
+    super::fx_rate::fx_rate::FxRateTable,
+    super::holdings::holding::Holding,
+    super::money::{Currency, Money},
+    super::store::lib::{HistoryQuery, UserStore},
+    super::templete::{TableRenderer, TableView, TemplateStruct},
+    super::trade_history::{cost_basis::CostBasis, trade_history::TradeHistory},
+    dividend_calendar::{month_index, month_start, ProjectedDividend, PROJECTION_MONTHS},
+};
+use crate::setting::HEADERS;
+use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+};
+use tera::{Context, Tera};
+
+// 月ごとの予想配当のグラフの棒1本
+#[derive(Debug, Serialize)]
+struct ChartBar {
+    month: String,  // 入金予定月
+    amount: String, // 整形済みの予想配当（円）
+    ratio: Decimal, // 最も多い月に対する割合[%]（棒の長さ）
+}
+
+// 保存済みの配当金の入金履歴から、今後12か月の配当の入金予定を表示する
+pub struct DividendCalendarManager {
+    template_struct: TemplateStruct,
+    dividend_list: RefCell<Vec<DividendList>>,
+    holdings: RefCell<Vec<Holding>>,
+    trade_history: RefCell<Vec<TradeHistory>>,
+    fx_rates: RefCell<FxRateTable>,
+    from: NaiveDate, // 予定を出す最初の月（今月）
+}
+
+impl DividendCalendarManager {
+    pub fn new() -> Self {
+        let today = Local::now().date_naive();
+        DividendCalendarManager {
+            template_struct: TemplateStruct::new(),
+            dividend_list: RefCell::new(Vec::new()),
+            holdings: RefCell::new(Vec::new()),
+            trade_history: RefCell::new(Vec::new()),
+            fx_rates: RefCell::new(FxRateTable::default()),
+            from: today.with_day(1).unwrap_or(today),
+        }
+    }
+
+    pub fn load(&self, store: &UserStore) -> Result<()> {
+        let query = HistoryQuery::default();
+        self.dividend_list
+            .borrow_mut()
+            .extend(store.load_dividend_list(&query)?);
+        self.holdings
+            .borrow_mut()
+            .extend(store.load_holdings(&query)?);
+        self.trade_history
+            .borrow_mut()
+            .extend(store.load_trade_history(&query)?);
+        *self.fx_rates.borrow_mut() = FxRateTable::new(&store.load_fx_rates(&query)?);
+        Ok(())
+    }
+
+    // 銘柄ごとの現在の保有数量。保有証券一覧はその時点の保有をすべて表すので、取り込み済みならそれだけを使う。
+    // なければ約定履歴から計算する（約定履歴にない銘柄は前回の入金時の数量を使う）
+    fn current_shares(&self) -> (HashMap<String, Decimal>, bool) {
+        let holdings = self.holdings.borrow();
+        if !holdings.is_empty() {
+            let mut shares: HashMap<String, Decimal> = HashMap::new();
+            for holding in holdings.iter() {
+                *shares
+                    .entry(security_key(
+                        holding.security_code.as_deref(),
+                        holding.security_name.as_deref(),
+                    ))
+                    .or_default() += Decimal::from(holding.shares.unwrap_or_default());
+            }
+            return (shares, true);
+        }
+
+        let cost_basis = CostBasis::calculate(&self.trade_history.borrow(), &[]);
+        let shares = CostBasis::current_positions(&cost_basis)
+            .into_iter()
+            .map(|(key, position)| (key, position.shares))
+            .collect();
+        (shares, false)
+    }
+
+    fn calculate_projections(&self) -> Vec<ProjectedDividend> {
+        let (current_shares, is_complete) = self.current_shares();
+        ProjectedDividend::project(
+            &self.dividend_list.borrow(),
+            &current_shares,
+            is_complete,
+            self.from,
+        )
+    }
+
+    // 入金予定月ごとの通貨ごとの合計
+    fn calculate_monthly_totals(
+        &self,
+        projections: &[ProjectedDividend],
+    ) -> BTreeMap<NaiveDate, BTreeMap<Currency, Decimal>> {
+        let mut totals: BTreeMap<NaiveDate, BTreeMap<Currency, Decimal>> = BTreeMap::new();
+        for projection in projections {
+            if let (Some(month), Some(dividend)) =
+                (projection.payment_month, &projection.projected_dividend)
+            {
+                *totals
+                    .entry(month)
+                    .or_default()
+                    .entry(dividend.currency.clone())
+                    .or_default() += dividend.amount;
+            }
+        }
+        totals
+    }
+
+    // 期間全体の通貨ごとの合計
+    fn calculate_grand_totals(&self, projections: &[ProjectedDividend]) -> Vec<ProjectedDividend> {
+        let mut totals: BTreeMap<Currency, Decimal> = BTreeMap::new();
+        for dividend in projections
+            .iter()
+            .filter_map(|projection| projection.projected_dividend.as_ref())
+        {
+            *totals.entry(dividend.currency.clone()).or_default() += dividend.amount;
+        }
+        totals
+            .into_iter()
+            .map(|(currency, total)| {
+                ProjectedDividend::new_total(None, Some("合計"), Money::new(total, currency))
+            })
+            .collect()
+    }
+
+    // 外貨建ての予想配当は最新のTTMで円に換算して円建てと合算する。
+    // レートを取り込んでいない通貨はグラフに含めない
+    fn generate_chart(&self, projections: &[ProjectedDividend]) -> Vec<ChartBar> {
+        let monthly_totals = self.calculate_monthly_totals(projections);
+        let fx_rates = self.fx_rates.borrow();
+        let from = month_index(self.from);
+        let amounts = (from..from + PROJECTION_MONTHS)
+            .filter_map(month_start)
+            .map(|month| {
+                let amount = monthly_totals
+                    .get(&month)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(currency, total)| {
+                        fx_rates
+                            .latest_rate(currency)
+                            .map(|rate| (*total * rate).trunc())
+                    })
+                    .sum::<Decimal>();
+                (month, amount)
+            })
+            .collect::<Vec<_>>();
+        let max = amounts
+            .iter()
+            .map(|(_, amount)| *amount)
+            .max()
+            .unwrap_or_default();
+
+        amounts
+            .into_iter()
+            .map(|(month, amount)| ChartBar {
+                month: month.format("%Y/%m").to_string(),
+                amount: self.format_number(&amount.round().to_string()),
+                ratio: match max.is_zero() {
+                    true => Decimal::ZERO,
+                    false => (amount / max * Decimal::ONE_HUNDRED).round_dp(1),
+                },
+            })
+            .collect()
+    }
+
+    // 為替レートがなく、グラフに含められない通貨
+    fn unconverted_currencies(&self, projections: &[ProjectedDividend]) -> Vec<String> {
+        let fx_rates = self.fx_rates.borrow();
+        projections
+            .iter()
+            .filter_map(|projection| projection.projected_dividend.as_ref())
+            .map(|dividend| &dividend.currency)
+            .filter(|currency| fx_rates.latest_rate(currency).is_none())
+            .map(|currency| currency.to_string())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    pub fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let projections = self.calculate_projections();
+        let headers = self.generate_table_header(ProjectedDividend::new().get_all_fields());
+
+        let monthly_totals = self.calculate_monthly_totals(&projections);
+        let mut rows = Vec::new();
+        for (month, totals) in &monthly_totals {
+            for projection in projections
+                .iter()
+                .filter(|projection| projection.payment_month == Some(*month))
+            {
+                rows.push(self.generate_table_row(&projection.get_all_fields()));
+            }
+            for (currency, total) in totals {
+                let total = ProjectedDividend::new_total(
+                    Some(*month),
+                    None,
+                    Money::new(*total, currency.clone()),
+                );
+                rows.push(
+                    self.generate_table_row_with_class("group-total", &total.get_all_fields()),
+                );
+            }
+        }
+        for total in self.calculate_grand_totals(&projections) {
+            rows.push(self.generate_table_row_with_class("grand-total", &total.get_all_fields()));
+        }
+        let table = TableView { headers, rows };
+
+        let mut context = Context::new();
+        context.insert("table", &table);
+        context.insert("chart", &self.generate_chart(&projections));
+        context.insert(
+            "unconverted_currencies",
+            &self.unconverted_currencies(&projections),
+        );
+        self.render_context(tera, "partials/dividend_calendar_table.html", context)
+    }
+
+    pub fn generate_csv(&self) -> Result<Vec<u8>> {
+        let mut writer = csv::Writer::from_writer(b"\xEF\xBB\xBF".to_vec());
+        let headers = ProjectedDividend::new()
+            .get_all_fields()
+            .into_iter()
+            .map(|(key, _)| HEADERS.get(&key).unwrap().to_string())
+            .collect::<Vec<_>>();
+        writer.write_record(&headers)?;
+        for projection in self.calculate_projections() {
+            writer.write_record(
+                projection
+                    .get_all_fields()
+                    .into_iter()
+                    .map(|(_, value)| value.unwrap_or_default()),
+            )?;
+        }
+        Ok(writer.into_inner()?)
+    }
+
+    pub fn generate_json(&self) -> Value {
+        let projections = self.calculate_projections();
+        let monthly_totals = self
+            .calculate_monthly_totals(&projections)
+            .into_iter()
+            .map(|(month, totals)| {
+                let totals = totals
+                    .into_iter()
+                    .map(|(currency, total)| Money::new(total, currency))
+                    .collect::<Vec<_>>();
+                json!({ "month": month, "totals": totals })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "from": self.from,
+            "projections": projections,
+            "monthly_totals": monthly_totals,
+            "grand_totals": self.calculate_grand_totals(&projections),
+        })
+    }
+}
+
+impl TableRenderer for DividendCalendarManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
+    }
+}
@@ -1,30 +1,52 @@
 use chrono::NaiveDate;
 use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde::Serialize;
 
-use crate::services::common;
+use crate::services::{
+    account::AccountType,
+    common::{ParseDiagnostic, RecordReader},
+    csv::column_map::ColumnMap,
+    fx_rate::fx_rate::FxRateTable,
+    money::{Currency, Money},
+};
 
-#[derive(Debug, Clone)]
+// 米国株の配当に米国で課される源泉税率（日米租税条約の限度税率）
+const US_WITHHOLDING_RATE: Decimal = Decimal::from_parts(10, 0, 0, false, 2);
+
+// 配当・分配金（税引前）、税額、受取金額の組
+pub type DividendAmounts = (Money, Money, Money);
+
+#[derive(Debug, Clone, Serialize)]
 pub struct DividendList {
-    pub settlement_date: Option<NaiveDate>,      // 入金日(受渡日)
-    pub product: Option<String>,                 // 商品
-    pub account: Option<String>,                 // 口座
-    pub security_code: Option<String>,           // 銘柄コード
-    pub security_name: Option<String>,           // 銘柄
-    pub currency: Option<String>,                // 受取通貨
-    pub unit_price: Option<String>,              // 単価[円/現地通貨]
-    pub shares: Option<i32>,                     // 数量[株/口]
-    pub dividends_before_tax: Option<i32>,       // 配当・分配金（税引前）[円/現地通貨]
-    pub taxes: Option<i32>,                      // 税額[円/現地通貨]
-    pub net_amount_received: Option<i32>,        // 受取金額[円/現地通貨]
-    pub total_dividends_before_tax: Option<i32>, // 配当・分配金合計（税引前）[円/現地通貨]
-    pub total_taxes: Option<i32>,                // 税額合計[円/現地通貨]
-    pub total_net_amount_received: Option<i32>,  // 受取金額合計[円/現地通貨]
+    pub settlement_date: Option<NaiveDate>,  // 入金日(受渡日)
+    pub payment_date: Option<NaiveDate>,     // 支払確定日（現地支払日）
+    pub product: Option<String>,             // 商品
+    pub account: Option<String>,             // 口座
+    pub security_code: Option<String>,       // 銘柄コード
+    pub security_name: Option<String>,       // 銘柄
+    pub currency: Option<String>,            // 受取通貨
+    pub unit_price: Option<Money>,           // 単価[円/現地通貨]
+    pub shares: Option<i32>,                 // 数量[株/口]
+    pub dividends_before_tax: Option<Money>, // 配当・分配金（税引前）[円/現地通貨]
+    pub taxes: Option<Money>,                // 税額[円/現地通貨]
+    pub foreign_taxes: Option<Money>,        // うち外国源泉税額[円/現地通貨]
+    pub domestic_taxes: Option<Money>,       // うち国内源泉税額[円/現地通貨]
+    pub net_amount_received: Option<Money>,  // 受取金額[円/現地通貨]
+    pub total_dividends_before_tax: Option<Money>, // 配当・分配金合計（税引前）[円/現地通貨]
+    pub total_taxes: Option<Money>,          // 税額合計[円/現地通貨]
+    pub total_net_amount_received: Option<Money>, // 受取金額合計[円/現地通貨]
+    pub fx_rate: Option<Decimal>,            // 換算日の為替レート（TTM）[円]
+    pub jpy_dividends_before_tax: Option<Money>, // 配当・分配金（税引前）の円換算額
+    pub jpy_taxes: Option<Money>,            // 税額の円換算額
+    pub jpy_net_amount_received: Option<Money>, // 受取金額の円換算額
 }
 
 impl DividendList {
     pub fn new() -> Self {
         DividendList {
             settlement_date: None,
+            payment_date: None,
             product: None,
             account: None,
             security_code: None,
@@ -34,51 +56,202 @@ impl DividendList {
             shares: None,
             dividends_before_tax: None,
             taxes: None,
+            foreign_taxes: None,
+            domestic_taxes: None,
             net_amount_received: None,
             total_dividends_before_tax: None,
             total_taxes: None,
             total_net_amount_received: None,
+            fx_rate: None,
+            jpy_dividends_before_tax: None,
+            jpy_taxes: None,
+            jpy_net_amount_received: None,
         }
     }
 
+    // 合計は通貨ごとに作るため、合計行の受取通貨欄には通貨コードを入れる。
+    // 円換算額の合計は、レートのない明細が1件でもあれば空欄にする
     pub fn new_total_dividend_list(
-        (total_dividends_before_tax, total_taxes, total_net_amount_received): (i32, i32, i32),
+        (total_dividends_before_tax, total_taxes, total_net_amount_received): DividendAmounts,
+        jpy_total: Option<DividendAmounts>,
     ) -> Self {
         DividendList {
-            settlement_date: None,
-            product: None,
-            account: None,
-            security_code: None,
-            security_name: None,
-            currency: None,
-            unit_price: None,
-            shares: None,
-            dividends_before_tax: None,
-            taxes: None,
-            net_amount_received: None,
+            currency: Some(total_dividends_before_tax.currency.to_string()),
             total_dividends_before_tax: Some(total_dividends_before_tax),
             total_taxes: Some(total_taxes),
             total_net_amount_received: Some(total_net_amount_received),
+            ..Self::new_jpy_total(jpy_total)
         }
     }
 
-    pub fn from_record(record: StringRecord) -> Self {
+    // 通貨の異なる合計を円換算額で合算した行。受取通貨欄には「円換算」と入れる
+    pub fn new_jpy_total(jpy_total: Option<DividendAmounts>) -> Self {
+        let label = match jpy_total {
+            Some(_) => "円換算",
+            None => "円換算(レートなし)",
+        };
         DividendList {
-            settlement_date: common::parse_date(record.get(0)),
-            product: common::parse_string(record.get(1)),
-            account: common::parse_string(record.get(2)),
-            security_code: common::parse_string(record.get(3)),
-            security_name: common::parse_string(record.get(4)),
-            currency: common::parse_string(record.get(5)),
-            unit_price: common::parse_string(record.get(6)),
-            shares: common::parse_int(record.get(7)),
-            dividends_before_tax: common::parse_int(record.get(8)),
-            taxes: common::parse_int(record.get(9)),
-            net_amount_received: common::parse_int(record.get(10)),
+            currency: Some(label.to_string()),
+            jpy_dividends_before_tax: jpy_total.as_ref().map(|total| total.0.clone()),
+            jpy_taxes: jpy_total.as_ref().map(|total| total.1.clone()),
+            jpy_net_amount_received: jpy_total.map(|total| total.2),
+            ..Self::new()
+        }
+    }
+
+    pub fn from_record(
+        record: &StringRecord,
+        columns: &ColumnMap,
+        diagnostics: &mut Vec<ParseDiagnostic>,
+    ) -> Self {
+        let mut reader = RecordReader::new(record, columns, diagnostics);
+        let currency = reader.string("currency");
+        let code = Currency::from_label(currency.as_deref().unwrap_or(""));
+        let dividend = DividendList {
+            settlement_date: reader.date("settlement_date"),
+            payment_date: reader.date("payment_date"),
+            product: reader.string("product"),
+            account: reader.string("account"),
+            security_code: reader.string("security_code"),
+            security_name: reader.string("security_name"),
+            currency,
+            unit_price: reader.money("unit_price", &code),
+            shares: reader.int("shares"),
+            dividends_before_tax: reader.money("dividends_before_tax", &code),
+            taxes: reader.money("taxes", &code),
+            foreign_taxes: reader.money("foreign_taxes", &code),
+            domestic_taxes: reader.money("domestic_taxes", &code),
+            net_amount_received: reader.money("net_amount_received", &code),
             total_dividends_before_tax: None,
             total_taxes: None,
             total_net_amount_received: None,
+            fx_rate: None,
+            jpy_dividends_before_tax: None,
+            jpy_taxes: None,
+            jpy_net_amount_received: None,
+        }
+        .with_tax_breakdown();
+
+        if dividend.foreign_taxes.is_none() && dividend.taxes.is_some() {
+            diagnostics.push(ParseDiagnostic::new(
+                record,
+                columns.header("taxes"),
+                columns.get(record, "taxes").unwrap_or(""),
+                "Tax breakdown unknown: foreign and domestic withholding left blank".to_string(),
+            ));
         }
+        dividend
+    }
+
+    // 税額を外国源泉税と国内源泉税に分ける。CSVに内訳があればそれを使い、
+    // なければ米ドル建ての配当は税引前配当の10%（日米租税条約の税率）を外国源泉税とし、残りを国内源泉税とする。
+    // 国内の銘柄の円建ての配当は、税額をすべて国内源泉税とする。
+    // 円で受け取った外国株の配当や米ドル以外の外貨建ての配当は、税率が分からないため空欄のままにする
+    pub fn with_tax_breakdown(self) -> Self {
+        let currency = self
+            .dividends_before_tax
+            .as_ref()
+            .map_or_else(Currency::jpy, |amount| amount.currency.clone());
+        let money = |amount: Decimal| Money::new(amount, currency.clone());
+
+        let (foreign_taxes, domestic_taxes, taxes) =
+            match (&self.foreign_taxes, &self.domestic_taxes, &self.taxes) {
+                (Some(foreign), Some(domestic), taxes) => (
+                    foreign.clone(),
+                    domestic.clone(),
+                    taxes
+                        .clone()
+                        .unwrap_or_else(|| money(foreign.amount + domestic.amount)),
+                ),
+                (Some(foreign), None, Some(taxes)) => (
+                    foreign.clone(),
+                    money(taxes.amount - foreign.amount),
+                    taxes.clone(),
+                ),
+                (None, Some(domestic), Some(taxes)) => (
+                    money(taxes.amount - domestic.amount),
+                    domestic.clone(),
+                    taxes.clone(),
+                ),
+                (None, None, Some(taxes)) if taxes.amount.is_zero() => {
+                    (taxes.clone(), taxes.clone(), taxes.clone())
+                }
+                (None, None, Some(taxes)) if currency == Currency::from_label("USD") => {
+                    let before_tax = amount_of(&self.dividends_before_tax);
+                    let foreign = (before_tax * US_WITHHOLDING_RATE).round_dp(2);
+                    let foreign = foreign.min(taxes.amount);
+                    (money(foreign), money(taxes.amount - foreign), taxes.clone())
+                }
+                (None, None, Some(taxes)) if currency.is_jpy() && !self.is_foreign_security() => {
+                    (money(Decimal::ZERO), taxes.clone(), taxes.clone())
+                }
+                _ => return self,
+            };
+        DividendList {
+            taxes: Some(taxes),
+            foreign_taxes: Some(foreign_taxes),
+            domestic_taxes: Some(domestic_taxes),
+            ..self
+        }
+    }
+
+    // 商品名が「米国株式」「外国株式」「海外ETF」などの外国の銘柄
+    fn is_foreign_security(&self) -> bool {
+        self.product.as_deref().is_some_and(|product| {
+            ["米国", "外国", "海外"]
+                .iter()
+                .any(|label| product.contains(label))
+        })
+    }
+
+    // 外貨建ての配当は、外国で源泉徴収された支払確定日のTTMで円に換算する。
+    // 支払確定日のないCSVから取り込んだ明細は、入金日で代用する
+    pub fn rate_date(&self) -> Option<NaiveDate> {
+        self.payment_date.or(self.settlement_date)
+    }
+
+    // 換算日の為替レートで円に換算する。レートがなければ円換算額は空欄のまま
+    pub fn with_jpy_amounts(self, fx_rates: &FxRateTable) -> Self {
+        let Some(rate_date) = self.rate_date() else {
+            return self;
+        };
+        let currency = self
+            .dividends_before_tax
+            .as_ref()
+            .map_or_else(Currency::jpy, |amount| amount.currency.clone());
+        let Some(fx_rate) = fx_rates.rate(&currency, rate_date) else {
+            return self;
+        };
+        let convert = |money: &Option<Money>| {
+            money
+                .as_ref()
+                .and_then(|money| fx_rates.convert(money, rate_date))
+        };
+        DividendList {
+            fx_rate: (!currency.is_jpy()).then_some(fx_rate),
+            jpy_dividends_before_tax: convert(&self.dividends_before_tax),
+            jpy_taxes: convert(&self.taxes),
+            jpy_net_amount_received: convert(&self.net_amount_received),
+            ..self
+        }
+    }
+
+    // 円建ての金額はそのまま、外貨建ての金額は換算日のレートで円に換算した額（円未満切り捨て）。
+    // with_jpy_amountsでレートが見つからなかった明細はNone
+    pub fn to_jpy(&self, money: &Option<Money>) -> Option<Decimal> {
+        let money = money.as_ref()?;
+        match money.currency.is_jpy() {
+            true => Some(money.amount),
+            false => self.fx_rate.map(|rate| (money.amount * rate).trunc()),
+        }
+    }
+
+    pub fn is_converted(&self) -> bool {
+        self.jpy_dividends_before_tax.is_some()
+    }
+
+    pub fn account_type(&self) -> AccountType {
+        AccountType::parse(self.account.as_deref())
     }
 
     pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
@@ -92,29 +265,146 @@ impl DividendList {
             ("security_code".to_string(), self.security_code.clone()),
             ("security_name".to_string(), self.security_name.clone()),
             ("currency".to_string(), self.currency.clone()),
-            ("unit_price".to_string(), self.unit_price.clone()),
+            (
+                "unit_price".to_string(),
+                self.unit_price.as_ref().map(|u| u.to_string()),
+            ),
             ("shares".to_string(), self.shares.map(|s| s.to_string())),
             (
                 "dividends_before_tax".to_string(),
-                self.dividends_before_tax.map(|t| t.to_string()),
+                self.dividends_before_tax.as_ref().map(|t| t.to_string()),
+            ),
+            (
+                "taxes".to_string(),
+                self.taxes.as_ref().map(|t| t.to_string()),
+            ),
+            (
+                "foreign_taxes".to_string(),
+                self.foreign_taxes.as_ref().map(|t| t.to_string()),
+            ),
+            (
+                "domestic_taxes".to_string(),
+                self.domestic_taxes.as_ref().map(|t| t.to_string()),
             ),
-            ("taxes".to_string(), self.taxes.map(|t| t.to_string())),
             (
                 "net_amount_received".to_string(),
-                self.net_amount_received.map(|n| n.to_string()),
+                self.net_amount_received.as_ref().map(|n| n.to_string()),
             ),
             (
                 "total_dividends_before_tax".to_string(),
-                self.total_dividends_before_tax.map(|t| t.to_string()),
+                self.total_dividends_before_tax
+                    .as_ref()
+                    .map(|t| t.to_string()),
             ),
             (
                 "total_taxes".to_string(),
-                self.total_taxes.map(|t| t.to_string()),
+                self.total_taxes.as_ref().map(|t| t.to_string()),
             ),
             (
                 "total_net_amount_received".to_string(),
-                self.total_net_amount_received.map(|n| n.to_string()),
+                self.total_net_amount_received
+                    .as_ref()
+                    .map(|n| n.to_string()),
+            ),
+            (
+                "fx_rate".to_string(),
+                self.fx_rate.map(|r| r.normalize().to_string()),
+            ),
+            (
+                "fx_rate_date".to_string(),
+                self.fx_rate.and(self.rate_date()).map(|d| d.to_string()),
+            ),
+            (
+                "jpy_dividends_before_tax".to_string(),
+                self.jpy_dividends_before_tax
+                    .as_ref()
+                    .map(|t| t.to_string()),
+            ),
+            (
+                "jpy_taxes".to_string(),
+                self.jpy_taxes.as_ref().map(|t| t.to_string()),
+            ),
+            (
+                "jpy_net_amount_received".to_string(),
+                self.jpy_net_amount_received.as_ref().map(|n| n.to_string()),
             ),
         ]
     }
 }
+
+pub fn amount_of(money: &Option<Money>) -> Decimal {
+    money.as_ref().map_or(Decimal::ZERO, |m| m.amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::csv::column_map::ColumnSpec;
+
+    const SPECS: &[ColumnSpec] = &[
+        ColumnSpec::required("settlement_date", &["入金日"]),
+        ColumnSpec::optional("product", &["商品"]),
+        ColumnSpec::optional("currency", &["受取通貨"]),
+        ColumnSpec::required("dividends_before_tax", &["配当・分配金（税引前）"]),
+        ColumnSpec::required("taxes", &["税額"]),
+    ];
+
+    fn parse(
+        product: &str,
+        currency: &str,
+        before_tax: &str,
+        taxes: &str,
+    ) -> (DividendList, usize) {
+        let headers = StringRecord::from(vec![
+            "入金日",
+            "商品",
+            "受取通貨",
+            "配当・分配金（税引前）",
+            "税額",
+        ]);
+        let columns = ColumnMap::new(SPECS, &headers).unwrap();
+        let record = StringRecord::from(vec!["2025/06/10", product, currency, before_tax, taxes]);
+        let mut diagnostics = Vec::new();
+        let dividend = DividendList::from_record(&record, &columns, &mut diagnostics);
+        (dividend, diagnostics.len())
+    }
+
+    fn amounts(dividend: &DividendList) -> (Option<Decimal>, Option<Decimal>) {
+        let amount = |money: &Option<Money>| money.as_ref().map(|m| m.amount);
+        (
+            amount(&dividend.foreign_taxes),
+            amount(&dividend.domestic_taxes),
+        )
+    }
+
+    #[test]
+    fn usd_dividend_is_split_at_the_treaty_rate() {
+        let (dividend, warnings) = parse("米国株式", "USドル", "27", "7.64");
+        assert_eq!(
+            amounts(&dividend),
+            (Some(Decimal::new(270, 2)), Some(Decimal::new(494, 2)))
+        );
+        assert_eq!(warnings, 0);
+    }
+
+    #[test]
+    fn domestic_yen_dividend_is_all_domestic_tax() {
+        let (dividend, warnings) = parse("国内株式", "円", "10000", "2031");
+        assert_eq!(
+            amounts(&dividend),
+            (Some(Decimal::ZERO), Some(Decimal::from(2031)))
+        );
+        assert_eq!(warnings, 0);
+    }
+
+    #[test]
+    fn yen_settled_foreign_and_non_usd_dividends_are_left_blank() {
+        let (dividend, warnings) = parse("米国株式", "円", "4000", "1212");
+        assert_eq!(amounts(&dividend), (None, None));
+        assert_eq!(warnings, 1);
+
+        let (dividend, warnings) = parse("外国株式", "香港ドル", "100", "30.3");
+        assert_eq!(amounts(&dividend), (None, None));
+        assert_eq!(warnings, 1);
+    }
+}
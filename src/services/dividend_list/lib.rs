@@ -1,83 +1,325 @@
 use super::{
-    super::templete::{TemplateManager, TemplateStruct},
-    dividend_list::DividendList,
+    super::account::AccountType,
+    super::broker::lib::BrokerAdapter,
+    super::common::ParseDiagnostic,
+    super::csv::column_map::{ColumnMap, ColumnSpec},
+    super::fx_rate::fx_rate::FxRateTable,
+    super::grouping::{self, GroupKey, Grouping},
+    super::money::{Currency, Money},
+    super::store::lib::{HistoryQuery, ImportSummary, UserStore},
+    super::templete::{RowView, TableRenderer, TableView, TemplateManager, TemplateStruct},
+    dividend_list::{amount_of, DividendAmounts, DividendList},
 };
 use anyhow::Result;
 use chrono::{Datelike, NaiveDate};
 use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
 use std::{cell::RefCell, collections::BTreeMap};
+use tera::Tera;
 
 pub struct DividendListManager {
-    _template_struct: TemplateStruct,
+    template_struct: TemplateStruct,
     dividend_list_map: RefCell<BTreeMap<NaiveDate, Vec<DividendList>>>,
 }
 
 impl DividendListManager {
     pub fn new() -> Self {
         DividendListManager {
-            _template_struct: TemplateStruct::new(),
+            template_struct: TemplateStruct::new(),
             dividend_list_map: RefCell::new(BTreeMap::new()),
         }
     }
 
-    fn generate_table_tbody(&self, table: &mut String, dividend_list: &[DividendList]) {
-        let mut total_dividends_before_tax = 0; // 配当・分配金合計（税引前）[円/現地通貨]
-        let mut total_taxes = 0; // 税額合計[円/現地通貨]
-        let mut total_net_amount_received = 0; // 受取金額[円/現地通貨]
+    fn insert(&self, settlement_date: NaiveDate, dividend: DividendList) {
+        self.dividend_list_map
+            .borrow_mut()
+            .entry(settlement_date)
+            .or_default()
+            .push(dividend);
+    }
+
+    // 未指定なら入金月ごとにまとめる
+    fn grouping(&self) -> Grouping {
+        self.template_struct
+            .grouping
+            .get()
+            .unwrap_or(Grouping::Month)
+    }
+
+    // 入金日順に振り分けるので、グループ内の明細も入金日順になる
+    fn group_dividend_list(&self) -> BTreeMap<GroupKey, Vec<DividendList>> {
+        let grouping = self.grouping();
+        let mut groups: BTreeMap<GroupKey, Vec<DividendList>> = BTreeMap::new();
+        for (settlement_date, dividend_list) in self.dividend_list_map.borrow().iter() {
+            for dividend in dividend_list {
+                let security = grouping::security_label(
+                    dividend.security_code.as_deref(),
+                    dividend.security_name.as_deref(),
+                );
+                let key = grouping.key(*settlement_date, &security, dividend.account.as_deref());
+                groups.entry(key).or_default().push(dividend.clone());
+            }
+        }
+        groups
+    }
+
+    fn all_dividend_list(&self) -> Vec<DividendList> {
+        self.dividend_list_map
+            .borrow()
+            .values()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    // 保存済みの為替レートで、入金日ごとに円換算額を求める
+    fn apply_fx_rates(&self, store: &UserStore) -> Result<()> {
+        let fx_rates = FxRateTable::new(&store.load_fx_rates(&HistoryQuery::default())?);
+        for dividend_list in self.dividend_list_map.borrow_mut().values_mut() {
+            *dividend_list = std::mem::take(dividend_list)
+                .into_iter()
+                .map(|dividend| dividend.with_jpy_amounts(&fx_rates))
+                .collect();
+        }
+        Ok(())
+    }
+
+    // 通貨の異なる金額は合算せず、通貨ごとに合計行を作る。
+    // 外貨建ての配当を含む場合は、円換算額を合算した行を最後に加える
+    fn calculate_group_totals(&self, dividend_list: &[DividendList]) -> Vec<DividendList> {
+        let mut totals: BTreeMap<Currency, ([Decimal; 3], Option<[Decimal; 3]>)> = BTreeMap::new();
 
         for dividend in dividend_list {
-            table.push_str(&self.generate_table_row(&dividend.get_all_fields()));
-
-            if let (Some(dividends_before_tax), Some(taxes), Some(net_amount_received)) = (
-                dividend.dividends_before_tax,
-                dividend.taxes,
-                dividend.net_amount_received,
-            ) {
-                total_dividends_before_tax += dividends_before_tax;
-                total_taxes += taxes;
-                total_net_amount_received += net_amount_received;
+            let amounts = [
+                &dividend.dividends_before_tax, // 配当・分配金合計（税引前）[円/現地通貨]
+                &dividend.taxes,                // 税額合計[円/現地通貨]
+                &dividend.net_amount_received,  // 受取金額[円/現地通貨]
+            ];
+            let Some(currency) = amounts.iter().flat_map(|amount| amount.as_ref()).next() else {
+                continue;
+            };
+            let (total, jpy_total) = totals
+                .entry(currency.currency.clone())
+                .or_insert(([Decimal::ZERO; 3], Some([Decimal::ZERO; 3])));
+            for (i, amount) in amounts.into_iter().enumerate() {
+                total[i] += amount_of(amount);
             }
+
+            let jpy_amounts = [
+                &dividend.jpy_dividends_before_tax,
+                &dividend.jpy_taxes,
+                &dividend.jpy_net_amount_received,
+            ];
+            *jpy_total = jpy_total
+                .filter(|_| dividend.is_converted())
+                .map(|mut jpy_total| {
+                    for (i, amount) in jpy_amounts.into_iter().enumerate() {
+                        jpy_total[i] += amount_of(amount);
+                    }
+                    jpy_total
+                });
+        }
+
+        let has_foreign_currency = totals.keys().any(|currency| !currency.is_jpy());
+        let jpy_grand_total =
+            totals
+                .values()
+                .try_fold([Decimal::ZERO; 3], |sum, (_, jpy_total)| {
+                    let jpy_total = (*jpy_total)?;
+                    Some([0, 1, 2].map(|i| sum[i] + jpy_total[i]))
+                });
+
+        let mut rows = totals
+            .into_iter()
+            .map(|(currency, (total, jpy_total))| {
+                DividendList::new_total_dividend_list(
+                    to_amounts(total, &currency),
+                    jpy_total.map(|jpy_total| to_amounts(jpy_total, &Currency::jpy())),
+                )
+            })
+            .collect::<Vec<_>>();
+        if has_foreign_currency {
+            rows.push(DividendList::new_jpy_total(
+                jpy_grand_total.map(|jpy_total| to_amounts(jpy_total, &Currency::jpy())),
+            ));
+        }
+        rows
+    }
+
+    // 損益通算の対象となる特定口座の円建て配当を年ごとに合計する
+    pub fn annual_specific_account_totals(&self) -> BTreeMap<i32, DividendList> {
+        let mut dividend_lists: BTreeMap<i32, Vec<DividendList>> = BTreeMap::new();
+        for dividend in self.dividend_list_map.borrow().values().flatten() {
+            let is_specific_account = dividend.account_type() == AccountType::Specific;
+            let is_jpy = dividend
+                .dividends_before_tax
+                .as_ref()
+                .is_some_and(|amount| amount.currency.is_jpy());
+            if let (Some(settlement_date), true, true) =
+                (dividend.settlement_date, is_specific_account, is_jpy)
+            {
+                dividend_lists
+                    .entry(settlement_date.year())
+                    .or_default()
+                    .push(dividend.clone());
+            }
+        }
+
+        dividend_lists
+            .into_iter()
+            .filter_map(|(year, dividend_list)| {
+                let total = self.calculate_group_totals(&dividend_list).pop()?;
+                Some((year, total))
+            })
+            .collect()
+    }
+
+    // 口座区分・通貨ごとの合計。口座欄に口座区分を入れる
+    fn calculate_account_type_totals(&self) -> Vec<DividendList> {
+        let dividend_list = self.all_dividend_list();
+        AccountType::ALL
+            .into_iter()
+            .flat_map(|account_type| {
+                let dividend_list = dividend_list
+                    .iter()
+                    .filter(|dividend| dividend.account_type() == account_type)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                self.calculate_group_totals(&dividend_list)
+                    .into_iter()
+                    .map(move |total| DividendList {
+                        account: Some(account_type.label().to_string()),
+                        ..total
+                    })
+            })
+            .collect()
+    }
+
+    // 通貨ごとの全体の合計
+    fn calculate_grand_totals(&self) -> Vec<DividendList> {
+        self.calculate_group_totals(&self.all_dividend_list())
+            .into_iter()
+            .map(|total| DividendList {
+                account: Some("合計".to_string()),
+                ..total
+            })
+            .collect()
+    }
+
+    fn generate_table_tbody(&self, rows: &mut Vec<RowView>, dividend_list: &[DividendList]) {
+        for dividend in dividend_list {
+            rows.push(self.generate_table_row(&dividend.get_all_fields()));
         }
 
-        let total = (
-            total_dividends_before_tax,
-            total_taxes,
-            total_net_amount_received,
-        );
-        let dividend_list = DividendList::new_total_dividend_list(total);
-        table.push_str(
-            &self.generate_table_row_with_class("group-total", &dividend_list.get_all_fields()),
-        );
+        for total in self.calculate_group_totals(dividend_list) {
+            rows.push(self.generate_table_row_with_class("group-total", &total.get_all_fields()));
+        }
+    }
+}
+
+fn to_amounts(
+    [dividends_before_tax, taxes, net_amount_received]: [Decimal; 3],
+    currency: &Currency,
+) -> DividendAmounts {
+    (
+        Money::new(dividends_before_tax, currency.clone()),
+        Money::new(taxes, currency.clone()),
+        Money::new(net_amount_received, currency.clone()),
+    )
+}
+
+impl TableRenderer for DividendListManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
     }
 }
 
 impl TemplateManager for DividendListManager {
-    fn process_records(&self, records: Vec<StringRecord>) {
+    fn updates_annual_results(&self) -> bool {
+        true
+    }
+
+    // 取り込んだ明細も保存済みの為替レートで円に換算する
+    fn persist(&self, store: &UserStore) -> Result<()> {
+        let summary = self.save(store)?;
+        self.template_struct().metadata.borrow_mut().stored = Some(summary);
+        self.apply_fx_rates(store)
+    }
+
+    fn column_specs(&self, broker: &dyn BrokerAdapter) -> &'static [ColumnSpec] {
+        broker.dividend_columns()
+    }
+
+    fn process_records(&self, records: Vec<StringRecord>, columns: &ColumnMap) {
+        let mut diagnostics = self.template_struct().diagnostics.borrow_mut();
         for record in records {
-            let dividend = DividendList::from_record(record);
+            let dividend = DividendList::from_record(&record, columns, &mut diagnostics);
+            let Some(settlement_date) = dividend.settlement_date else {
+                ParseDiagnostic::push_row_skipped(
+                    &mut diagnostics,
+                    &record,
+                    columns,
+                    "settlement_date",
+                    "Row skipped: missing settlement date",
+                );
+                continue;
+            };
+            self.insert(settlement_date, dividend);
+        }
+    }
+
+    fn save(&self, store: &UserStore) -> Result<ImportSummary> {
+        store.save_dividend_list(self.dividend_list_map.borrow().values().flatten())
+    }
+
+    fn load(&self, store: &UserStore, query: &HistoryQuery) -> Result<()> {
+        for dividend in store.load_dividend_list(query)? {
             if let Some(settlement_date) = dividend.settlement_date {
-                let date =
-                    NaiveDate::from_ymd_opt(settlement_date.year(), settlement_date.month(), 1)
-                        .unwrap();
-                self.dividend_list_map
-                    .borrow_mut()
-                    .entry(date)
-                    .or_insert_with(Vec::new)
-                    .push(dividend);
+                self.insert(settlement_date, dividend);
             }
         }
+        self.apply_fx_rates(store)
     }
 
-    fn generate_html_table(&self) -> Result<String> {
-        let headers = DividendList::new().get_all_fields();
-        let mut table = self.generate_table_header(headers);
+    fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let headers = self.generate_table_header(DividendList::new().get_all_fields());
 
-        table.push_str("<tbody>");
-        for (_, dividend_list) in self.dividend_list_map.borrow().iter() {
-            let _total = self.generate_table_tbody(&mut table, dividend_list);
+        let mut rows = Vec::new();
+        for dividend_list in self.group_dividend_list().values() {
+            self.generate_table_tbody(&mut rows, dividend_list);
+        }
+        for total in self.calculate_account_type_totals() {
+            rows.push(self.generate_table_row_with_class("summary-total", &total.get_all_fields()));
+        }
+        for total in self.calculate_grand_totals() {
+            rows.push(self.generate_table_row_with_class("grand-total", &total.get_all_fields()));
         }
-        table.push_str("</tbody></table>");
 
-        Ok(table)
+        let table = TableView { headers, rows };
+        self.render_table(tera, "partials/dividend_table.html", &table)
+    }
+
+    fn generate_json(&self) -> Result<Value> {
+        let grouping = self.grouping();
+        let groups = self
+            .group_dividend_list()
+            .iter()
+            .map(|(key, dividend_list)| {
+                json!({
+                    "key": key,
+                    "label": grouping.label(key),
+                    "rows": dividend_list,
+                    "totals": self.calculate_group_totals(dividend_list),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(json!({
+            "grouping": grouping,
+            "groups": groups,
+            "account_type_totals": self.calculate_account_type_totals(),
+            "grand_totals": self.calculate_grand_totals(),
+        }))
     }
 }
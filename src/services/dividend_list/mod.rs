@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 pub mod dividend_list;
 pub mod lib;
@@ -0,0 +1,171 @@
+use chrono::Datelike;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::services::{
+    common::rate,
+    dividend_list::dividend_list::DividendList,
+    money::{Currency, Money},
+};
+
+// 銘柄・年ごとの配当の集計
+#[derive(Debug, Clone, Serialize)]
+pub struct DividendSummary {
+    pub security_code: Option<String>,             // 銘柄コード
+    pub security_name: Option<String>,             // 銘柄名
+    pub year: Option<i32>,                         // 年
+    pub currency: Option<String>,                  // 受取通貨
+    pub payments: Option<usize>,                   // 支払回数
+    pub total_dividends_before_tax: Option<Money>, // 配当・分配金合計（税引前）
+    pub total_net_amount_received: Option<Money>,  // 受取金額合計
+    pub dividend_per_share: Option<Money>,         // 1株あたり配当（税引前）
+    pub dividend_growth_rate: Option<Decimal>,     // 1株あたり配当の前年比[%]
+    pub cost_per_share: Option<Money>,             // 1株あたりの取得単価
+    pub yield_on_cost: Option<Decimal>,            // 取得単価に対する利回り[%]
+}
+
+// 1年分の支払いを足し合わせる途中の値
+#[derive(Debug, Default)]
+struct AnnualDividend {
+    security_code: Option<String>,
+    security_name: Option<String>,
+    payments: usize,
+    before_tax: Decimal,
+    net_amount: Decimal,
+    per_share: Option<Decimal>,
+}
+
+impl DividendSummary {
+    pub fn new() -> Self {
+        DividendSummary {
+            security_code: None,
+            security_name: None,
+            year: None,
+            currency: None,
+            payments: None,
+            total_dividends_before_tax: None,
+            total_net_amount_received: None,
+            dividend_per_share: None,
+            dividend_growth_rate: None,
+            cost_per_share: None,
+            yield_on_cost: None,
+        }
+    }
+
+    // 銘柄・通貨ごとに年単位で集計する。1株あたり配当は支払いごとの「税引前配当 / 数量」の合計で、
+    // 数量の分からない支払いは含めない。取得単価は円建てのため、利回りは円で受け取った配当だけ計算する
+    pub fn calculate(
+        dividend_list: &[DividendList],
+        cost_per_share: &HashMap<String, Decimal>,
+    ) -> Vec<Self> {
+        let mut groups: BTreeMap<(String, Currency), BTreeMap<i32, AnnualDividend>> =
+            BTreeMap::new();
+        for dividend in dividend_list {
+            let (Some(settlement_date), Some(before_tax)) =
+                (dividend.settlement_date, &dividend.dividends_before_tax)
+            else {
+                continue;
+            };
+            let key = security_key(
+                dividend.security_code.as_deref(),
+                dividend.security_name.as_deref(),
+            );
+            let annual = groups
+                .entry((key, before_tax.currency.clone()))
+                .or_default()
+                .entry(settlement_date.year())
+                .or_default();
+            annual.security_code = annual
+                .security_code
+                .take()
+                .or_else(|| dividend.security_code.clone());
+            annual.security_name = annual
+                .security_name
+                .take()
+                .or_else(|| dividend.security_name.clone());
+            annual.payments += 1;
+            annual.before_tax += before_tax.amount;
+            annual.net_amount += dividend
+                .net_amount_received
+                .as_ref()
+                .map_or(Decimal::ZERO, |m| m.amount);
+            if let Some(shares) = dividend.shares.filter(|shares| *shares > 0) {
+                *annual.per_share.get_or_insert(Decimal::ZERO) +=
+                    before_tax.amount / Decimal::from(shares);
+            }
+        }
+
+        let mut rows = Vec::new();
+        for ((key, currency), years) in groups {
+            let cost = cost_per_share
+                .get(&key)
+                .copied()
+                .filter(|_| currency.is_jpy());
+            for (year, annual) in &years {
+                let previous = years
+                    .get(&(year - 1))
+                    .and_then(|previous| previous.per_share);
+                let money = |amount: Decimal| Money::new(amount, currency.clone());
+                rows.push(DividendSummary {
+                    security_code: annual.security_code.clone(),
+                    security_name: annual.security_name.clone(),
+                    year: Some(*year),
+                    currency: Some(currency.to_string()),
+                    payments: Some(annual.payments),
+                    total_dividends_before_tax: Some(money(annual.before_tax)),
+                    total_net_amount_received: Some(money(annual.net_amount)),
+                    dividend_per_share: annual.per_share.map(|p| money(p.round_dp(2))),
+                    dividend_growth_rate: rate(
+                        annual.per_share.zip(previous).map(|(p, prev)| p - prev),
+                        previous,
+                    ),
+                    cost_per_share: cost.map(|c| money(c.round_dp(2))),
+                    yield_on_cost: rate(annual.per_share, cost),
+                });
+            }
+        }
+
+        rows
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        let money = |m: &Option<Money>| m.as_ref().map(|m| m.to_string());
+        vec![
+            ("security_code".to_string(), self.security_code.clone()),
+            ("security_name".to_string(), self.security_name.clone()),
+            ("year".to_string(), self.year.map(|y| y.to_string())),
+            ("currency".to_string(), self.currency.clone()),
+            ("payments".to_string(), self.payments.map(|p| p.to_string())),
+            (
+                "total_dividends_before_tax".to_string(),
+                money(&self.total_dividends_before_tax),
+            ),
+            (
+                "total_net_amount_received".to_string(),
+                money(&self.total_net_amount_received),
+            ),
+            (
+                "dividend_per_share".to_string(),
+                money(&self.dividend_per_share),
+            ),
+            (
+                "dividend_growth_rate".to_string(),
+                self.dividend_growth_rate.map(|r| r.to_string()),
+            ),
+            ("cost_per_share".to_string(), money(&self.cost_per_share)),
+            (
+                "yield_on_cost".to_string(),
+                self.yield_on_cost.map(|r| r.to_string()),
+            ),
+        ]
+    }
+}
+
+// 保有証券・約定履歴と突き合わせる銘柄のキー。銘柄コードがなければ銘柄名を使う
+pub fn security_key(security_code: Option<&str>, security_name: Option<&str>) -> String {
+    security_code
+        .or(security_name)
+        .unwrap_or_default()
+        .to_string()
+}
@@ -0,0 +1,111 @@
+use super::{
+    super::dividend_list::dividend_list::DividendList,
+    super::holdings::holding::Holding,
+    super::store::lib::{HistoryQuery, UserStore},
+    super::templete::{TableRenderer, TableView, TemplateStruct},
+    super::trade_history::{cost_basis::CostBasis, trade_history::TradeHistory},
+    dividend_summary::{security_key, DividendSummary},
+};
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use std::{cell::RefCell, collections::HashMap};
+use tera::Tera;
+
+// 保存済みの配当金の明細を銘柄・年ごとに集計し、取得単価に対する利回りを表示する
+pub struct DividendSummaryManager {
+    template_struct: TemplateStruct,
+    dividend_list: RefCell<Vec<DividendList>>,
+    holdings: RefCell<Vec<Holding>>,
+    trade_history: RefCell<Vec<TradeHistory>>,
+}
+
+impl DividendSummaryManager {
+    pub fn new() -> Self {
+        DividendSummaryManager {
+            template_struct: TemplateStruct::new(),
+            dividend_list: RefCell::new(Vec::new()),
+            holdings: RefCell::new(Vec::new()),
+            trade_history: RefCell::new(Vec::new()),
+        }
+    }
+
+    // 期間の指定は配当金の明細にだけ使う。取得単価は過去の約定をすべて使って計算する
+    pub fn load(&self, store: &UserStore, query: &HistoryQuery) -> Result<()> {
+        let security_query = HistoryQuery {
+            security_code: query.security_code.clone(),
+            ..HistoryQuery::default()
+        };
+        self.dividend_list
+            .borrow_mut()
+            .extend(store.load_dividend_list(query)?);
+        self.holdings
+            .borrow_mut()
+            .extend(store.load_holdings(&security_query)?);
+        self.trade_history
+            .borrow_mut()
+            .extend(store.load_trade_history(&security_query)?);
+        Ok(())
+    }
+
+    // 銘柄ごとの1株あたりの取得単価。保有証券一覧の取得金額を優先し、
+    // 保有証券一覧にない銘柄は約定履歴から再計算した平均取得単価を使う
+    fn cost_per_share(&self) -> HashMap<String, Decimal> {
+        let trade_history = self.trade_history.borrow();
+        let cost_basis = CostBasis::calculate(&trade_history, &[]);
+        let mut costs = CostBasis::current_positions(&cost_basis)
+            .into_iter()
+            .filter(|(_, position)| !position.shares.is_zero())
+            .map(|(key, position)| (key, position.average_cost))
+            .collect::<HashMap<_, _>>();
+
+        let mut holdings: HashMap<String, (Decimal, Decimal)> = HashMap::new();
+        for holding in self.holdings.borrow().iter() {
+            let (Some(shares), Some(acquisition_amount)) =
+                (holding.shares, &holding.acquisition_amount)
+            else {
+                continue;
+            };
+            let total = holdings
+                .entry(security_key(
+                    holding.security_code.as_deref(),
+                    holding.security_name.as_deref(),
+                ))
+                .or_default();
+            total.0 += Decimal::from(shares);
+            total.1 += acquisition_amount.amount;
+        }
+        for (key, (shares, acquisition_amount)) in holdings {
+            if !shares.is_zero() {
+                costs.insert(key, acquisition_amount / shares);
+            }
+        }
+        costs
+    }
+
+    fn calculate_summaries(&self) -> Vec<DividendSummary> {
+        DividendSummary::calculate(&self.dividend_list.borrow(), &self.cost_per_share())
+    }
+
+    pub fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let headers = self.generate_table_header(DividendSummary::new().get_all_fields());
+        let rows = self
+            .calculate_summaries()
+            .iter()
+            .map(|summary| self.generate_table_row(&summary.get_all_fields()))
+            .collect();
+
+        let table = TableView { headers, rows };
+        self.render_table(tera, "partials/dividend_summary_table.html", &table)
+    }
+
+    pub fn generate_json(&self) -> Value {
+        json!({ "summaries": self.calculate_summaries() })
+    }
+}
+
+impl TableRenderer for DividendSummaryManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
+    }
+}
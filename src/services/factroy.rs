@@ -1,12 +1,33 @@
 use super::{
-    dividend_list::lib::DividendListManager, profit_and_loss::lib::ProfitAndLossManager,
-    templete::TemplateManager,
+    broker::lib::find_broker, dividend_list::lib::DividendListManager, fx_rate::lib::FxRateManager,
+    grouping::Grouping, holdings::lib::HoldingsManager, profit_and_loss::lib::ProfitAndLossManager,
+    templete::TemplateManager, trade_history::lib::TradeHistoryManager,
 };
+use anyhow::{anyhow, Result};
 
-pub fn create_factory(id: &str) -> Box<dyn TemplateManager> {
-    match id {
+// brokerを省略した場合は、読み込んだCSVのヘッダー行から証券会社を判定する
+pub fn create_factory(
+    id: &str,
+    broker: Option<&str>,
+    grouping: Option<Grouping>,
+) -> Result<Box<dyn TemplateManager + Send>> {
+    let manager: Box<dyn TemplateManager + Send> = match id {
         "dividend" => Box::new(DividendListManager::new()),
         "profit-loss" => Box::new(ProfitAndLossManager::new()),
-        _ => panic!("Unknown id"),
+        "trade-history" => Box::new(TradeHistoryManager::new()),
+        "holdings" => Box::new(HoldingsManager::new()),
+        "fx-rate" => Box::new(FxRateManager::new()),
+        _ => return Err(anyhow!("Unknown id '{}'", id)),
+    };
+
+    if let Some(broker) = broker.filter(|broker| !broker.is_empty()) {
+        manager
+            .template_struct()
+            .broker
+            .set(Some(find_broker(broker)?));
     }
+
+    manager.template_struct().grouping.set(grouping);
+
+    Ok(manager)
 }
@@ -0,0 +1,235 @@
+use chrono::Datelike;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::services::{
+    dividend_list::dividend_list::{amount_of, DividendList},
+    money::{Currency, Money},
+};
+
+// 年・通貨ごとの、外国税額控除の対象となる外国源泉税の集計
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignTaxCredit {
+    pub year: Option<i32>,                       // 年
+    pub currency: Option<String>,                // 受取通貨
+    pub payments: Option<usize>,                 // 支払回数
+    pub dividends_before_tax: Option<Money>,     // 配当・分配金（税引前）[円/現地通貨]
+    pub foreign_taxes: Option<Money>,            // 外国源泉税額[円/現地通貨]
+    pub jpy_dividends_before_tax: Option<Money>, // 配当・分配金（税引前）の円換算額
+    pub jpy_foreign_taxes: Option<Money>,        // 外国源泉税額の円換算額
+    pub missing_rates: Option<usize>,            // 為替レートがなく円換算できなかった支払いの件数
+    pub settlement_date_rates: Option<usize>, // 支払確定日がなく入金日のレートで換算した支払いの件数
+}
+
+// 1年分・1通貨分の支払いを足し合わせる途中の値
+#[derive(Debug, Default)]
+struct AnnualForeignTax {
+    payments: usize,
+    before_tax: Decimal,
+    foreign_taxes: Decimal,
+    jpy_before_tax: Decimal,
+    jpy_foreign_taxes: Decimal,
+    missing_rates: usize,
+    settlement_date_rates: usize,
+}
+
+impl ForeignTaxCredit {
+    pub fn new() -> Self {
+        ForeignTaxCredit {
+            year: None,
+            currency: None,
+            payments: None,
+            dividends_before_tax: None,
+            foreign_taxes: None,
+            jpy_dividends_before_tax: None,
+            jpy_foreign_taxes: None,
+            missing_rates: None,
+            settlement_date_rates: None,
+        }
+    }
+
+    // 課税口座で受け取った、外国源泉税のある配当を年・通貨ごとに集計する。
+    // NISA口座の配当は国内で課税されないため、外国源泉税は控除の対象にならない。
+    // 円換算は配当の明細と同じく支払いごとに支払確定日（なければ入金日）のTTMで行う。
+    // with_jpy_amountsで換算済みの明細を受け取り、レートのない支払いは円換算額に含めない
+    pub fn calculate(dividend_list: &[DividendList]) -> Vec<Self> {
+        let mut annual: BTreeMap<(i32, Currency), AnnualForeignTax> = BTreeMap::new();
+        for dividend in dividend_list {
+            let (Some(settlement_date), Some(foreign_taxes)) =
+                (dividend.settlement_date, &dividend.foreign_taxes)
+            else {
+                continue;
+            };
+            if !dividend.account_type().is_taxable() || foreign_taxes.amount.is_zero() {
+                continue;
+            }
+
+            let total = annual
+                .entry((settlement_date.year(), foreign_taxes.currency.clone()))
+                .or_default();
+            total.payments += 1;
+            total.before_tax += amount_of(&dividend.dividends_before_tax);
+            total.foreign_taxes += foreign_taxes.amount;
+
+            match dividend.to_jpy(&dividend.foreign_taxes) {
+                Some(jpy_foreign_taxes) => {
+                    let jpy_before_tax = dividend.to_jpy(&dividend.dividends_before_tax);
+                    total.jpy_before_tax += jpy_before_tax.unwrap_or_default();
+                    total.jpy_foreign_taxes += jpy_foreign_taxes;
+                    if dividend.payment_date.is_none() {
+                        total.settlement_date_rates += 1;
+                    }
+                }
+                None => total.missing_rates += 1,
+            }
+        }
+
+        annual
+            .into_iter()
+            .map(|((year, currency), total)| {
+                let money = |amount: Decimal| Some(Money::new(amount, currency.clone()));
+                ForeignTaxCredit {
+                    year: Some(year),
+                    currency: Some(currency.to_string()),
+                    payments: Some(total.payments),
+                    dividends_before_tax: money(total.before_tax),
+                    foreign_taxes: money(total.foreign_taxes),
+                    jpy_dividends_before_tax: Some(Money::jpy(total.jpy_before_tax)),
+                    jpy_foreign_taxes: Some(Money::jpy(total.jpy_foreign_taxes)),
+                    missing_rates: Some(total.missing_rates),
+                    settlement_date_rates: Some(total.settlement_date_rates),
+                }
+            })
+            .collect()
+    }
+
+    // 通貨の異なる集計を円換算額で合算した年の合計。受取通貨欄には「円換算」と入れる
+    pub fn new_annual_total(year: i32, credits: &[&ForeignTaxCredit]) -> Self {
+        let sum = |amount: fn(&ForeignTaxCredit) -> &Option<Money>| {
+            Some(Money::jpy(
+                credits.iter().map(|credit| amount_of(amount(credit))).sum(),
+            ))
+        };
+        ForeignTaxCredit {
+            year: Some(year),
+            currency: Some("円換算".to_string()),
+            payments: Some(credits.iter().flat_map(|credit| credit.payments).sum()),
+            jpy_dividends_before_tax: sum(|credit| &credit.jpy_dividends_before_tax),
+            jpy_foreign_taxes: sum(|credit| &credit.jpy_foreign_taxes),
+            missing_rates: Some(credits.iter().flat_map(|credit| credit.missing_rates).sum()),
+            settlement_date_rates: Some(
+                credits
+                    .iter()
+                    .flat_map(|credit| credit.settlement_date_rates)
+                    .sum(),
+            ),
+            ..Self::new()
+        }
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        let money = |m: &Option<Money>| m.as_ref().map(|m| m.to_string());
+        vec![
+            ("year".to_string(), self.year.map(|y| y.to_string())),
+            ("currency".to_string(), self.currency.clone()),
+            ("payments".to_string(), self.payments.map(|p| p.to_string())),
+            (
+                "dividends_before_tax".to_string(),
+                money(&self.dividends_before_tax),
+            ),
+            ("foreign_taxes".to_string(), money(&self.foreign_taxes)),
+            (
+                "jpy_dividends_before_tax".to_string(),
+                money(&self.jpy_dividends_before_tax),
+            ),
+            (
+                "jpy_foreign_taxes".to_string(),
+                money(&self.jpy_foreign_taxes),
+            ),
+            (
+                "missing_rates".to_string(),
+                self.missing_rates.map(|m| m.to_string()),
+            ),
+            (
+                "settlement_date_rates".to_string(),
+                self.settlement_date_rates.map(|s| s.to_string()),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::fx_rate::fx_rate::{FxRate, FxRateTable};
+    use chrono::NaiveDate;
+
+    fn date(month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(2025, month, day)
+    }
+
+    fn usd(amount: i64) -> Option<Money> {
+        Some(Money::new(
+            Decimal::from(amount),
+            Currency::from_label("USD"),
+        ))
+    }
+
+    fn dividend(payment_date: Option<NaiveDate>) -> DividendList {
+        DividendList {
+            settlement_date: date(4, 10),
+            payment_date,
+            account: Some("特定口座".to_string()),
+            currency: Some("USドル".to_string()),
+            dividends_before_tax: usd(100),
+            taxes: usd(30),
+            foreign_taxes: usd(10),
+            domestic_taxes: usd(20),
+            ..DividendList::new()
+        }
+    }
+
+    fn credits(dividend_list: Vec<DividendList>) -> Vec<ForeignTaxCredit> {
+        let rate = |rate_date, ttm| FxRate {
+            rate_date,
+            currency: Some(Currency::from_label("USD")),
+            ttm: Some(Decimal::from(ttm)),
+        };
+        let fx_rates = FxRateTable::new(&[rate(date(3, 28), 150), rate(date(4, 10), 145)]);
+        let dividend_list = dividend_list
+            .into_iter()
+            .map(|dividend| dividend.with_jpy_amounts(&fx_rates))
+            .collect::<Vec<_>>();
+        ForeignTaxCredit::calculate(&dividend_list)
+    }
+
+    #[test]
+    fn converts_at_the_payment_date_rate() {
+        let credits = credits(vec![dividend(date(3, 28))]);
+
+        assert_eq!(credits.len(), 1);
+        assert_eq!(
+            credits[0].jpy_dividends_before_tax,
+            Some(Money::jpy(Decimal::from(15_000)))
+        );
+        assert_eq!(
+            credits[0].jpy_foreign_taxes,
+            Some(Money::jpy(Decimal::from(1_500)))
+        );
+        assert_eq!(credits[0].settlement_date_rates, Some(0));
+    }
+
+    #[test]
+    fn falls_back_to_the_settlement_date_rate_without_a_payment_date() {
+        let credits = credits(vec![dividend(date(3, 28)), dividend(None)]);
+
+        assert_eq!(credits[0].payments, Some(2));
+        assert_eq!(
+            credits[0].jpy_foreign_taxes,
+            Some(Money::jpy(Decimal::from(1_500 + 1_450)))
+        );
+        assert_eq!(credits[0].settlement_date_rates, Some(1));
+        assert_eq!(credits[0].missing_rates, Some(0));
+    }
+}
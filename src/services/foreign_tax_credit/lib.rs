@@ -0,0 +1,91 @@
+use super::{
+    super::dividend_list::dividend_list::DividendList,
+    super::fx_rate::fx_rate::FxRateTable,
+    super::store::lib::{HistoryQuery, UserStore},
+    super::templete::{TableRenderer, TableView, TemplateStruct},
+    foreign_tax_credit::ForeignTaxCredit,
+};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use tera::Tera;
+
+// 保存済みの配当金の明細から、確定申告で外国税額控除を受けられる外国源泉税を年ごとに表示する
+pub struct ForeignTaxCreditManager {
+    template_struct: TemplateStruct,
+    dividend_list: RefCell<Vec<DividendList>>,
+}
+
+impl ForeignTaxCreditManager {
+    pub fn new() -> Self {
+        ForeignTaxCreditManager {
+            template_struct: TemplateStruct::new(),
+            dividend_list: RefCell::new(Vec::new()),
+        }
+    }
+
+    // 期間の指定は配当金の明細にだけ使う。為替レートは換算日より前のものも使うため、すべて読み込む
+    pub fn load(&self, store: &UserStore, query: &HistoryQuery) -> Result<()> {
+        let fx_rates = FxRateTable::new(&store.load_fx_rates(&HistoryQuery::default())?);
+        self.dividend_list.borrow_mut().extend(
+            store
+                .load_dividend_list(query)?
+                .into_iter()
+                .map(|dividend| dividend.with_jpy_amounts(&fx_rates)),
+        );
+        Ok(())
+    }
+
+    fn calculate_credits(&self) -> Vec<ForeignTaxCredit> {
+        ForeignTaxCredit::calculate(&self.dividend_list.borrow())
+    }
+
+    // 年ごとの円換算額の合計。確定申告ではこの金額を外国税額控除の対象として記入する
+    fn calculate_annual_totals(&self, credits: &[ForeignTaxCredit]) -> Vec<ForeignTaxCredit> {
+        let mut years = credits
+            .iter()
+            .filter_map(|credit| credit.year)
+            .collect::<Vec<_>>();
+        years.dedup();
+        years
+            .into_iter()
+            .map(|year| {
+                let credits = credits
+                    .iter()
+                    .filter(|credit| credit.year == Some(year))
+                    .collect::<Vec<_>>();
+                ForeignTaxCredit::new_annual_total(year, &credits)
+            })
+            .collect()
+    }
+
+    pub fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let credits = self.calculate_credits();
+        let headers = self.generate_table_header(ForeignTaxCredit::new().get_all_fields());
+
+        let mut rows = Vec::new();
+        for total in self.calculate_annual_totals(&credits) {
+            for credit in credits.iter().filter(|credit| credit.year == total.year) {
+                rows.push(self.generate_table_row(&credit.get_all_fields()));
+            }
+            rows.push(self.generate_table_row_with_class("group-total", &total.get_all_fields()));
+        }
+
+        let table = TableView { headers, rows };
+        self.render_table(tera, "partials/foreign_tax_credit_table.html", &table)
+    }
+
+    pub fn generate_json(&self) -> Value {
+        let credits = self.calculate_credits();
+        json!({
+            "annual_totals": self.calculate_annual_totals(&credits),
+            "credits": credits,
+        })
+    }
+}
+
+impl TableRenderer for ForeignTaxCreditManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
+    }
+}
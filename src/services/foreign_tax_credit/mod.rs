@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod foreign_tax_credit;
+pub mod lib;
@@ -0,0 +1,117 @@
+use chrono::NaiveDate;
+use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::services::{
+    common::{ParseDiagnostic, RecordReader},
+    csv::column_map::ColumnMap,
+    money::{Currency, Money},
+};
+
+// 入金日のレートがないとき（休日など）に使う、それより前のレートの古さの上限[日]
+const MAX_RATE_AGE_DAYS: i64 = 7;
+
+// 1日分・1通貨分の為替レート
+#[derive(Debug, Clone, Serialize)]
+pub struct FxRate {
+    pub rate_date: Option<NaiveDate>, // 適用日
+    pub currency: Option<Currency>,   // 通貨
+    pub ttm: Option<Decimal>,         // TTM（仲値）[円]
+}
+
+impl FxRate {
+    pub fn new() -> Self {
+        FxRate {
+            rate_date: None,
+            currency: None,
+            ttm: None,
+        }
+    }
+
+    // TTMの列がなければ、TTSとTTBの中間をTTMとする
+    pub fn from_record(
+        record: &StringRecord,
+        columns: &ColumnMap,
+        diagnostics: &mut Vec<ParseDiagnostic>,
+    ) -> Self {
+        let mut reader = RecordReader::new(record, columns, diagnostics);
+        let ttm = reader.decimal("ttm");
+        let tts = reader.decimal("tts");
+        let ttb = reader.decimal("ttb");
+        FxRate {
+            rate_date: reader.date("rate_date"),
+            currency: reader
+                .string("currency")
+                .map(|currency| Currency::from_label(&currency)),
+            ttm: ttm.or_else(|| tts.zip(ttb).map(|(tts, ttb)| (tts + ttb) / Decimal::TWO)),
+        }
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        vec![
+            (
+                "rate_date".to_string(),
+                self.rate_date.map(|d| d.to_string()),
+            ),
+            (
+                "currency_code".to_string(),
+                self.currency.as_ref().map(|c| c.to_string()),
+            ),
+            (
+                "ttm".to_string(),
+                self.ttm.map(|t| t.normalize().to_string()),
+            ),
+        ]
+    }
+}
+
+// 通貨ごと・日付ごとのTTM。円はレート1として扱う
+#[derive(Debug, Default)]
+pub struct FxRateTable {
+    rates: HashMap<Currency, BTreeMap<NaiveDate, Decimal>>,
+}
+
+impl FxRateTable {
+    pub fn new<'a>(fx_rates: impl IntoIterator<Item = &'a FxRate>) -> Self {
+        let mut rates: HashMap<Currency, BTreeMap<NaiveDate, Decimal>> = HashMap::new();
+        for fx_rate in fx_rates {
+            if let (Some(rate_date), Some(currency), Some(ttm)) =
+                (fx_rate.rate_date, &fx_rate.currency, fx_rate.ttm)
+            {
+                rates
+                    .entry(currency.clone())
+                    .or_default()
+                    .insert(rate_date, ttm);
+            }
+        }
+        FxRateTable { rates }
+    }
+
+    // その日のレート。なければ直近7日以内の前のレートを使う
+    pub fn rate(&self, currency: &Currency, date: NaiveDate) -> Option<Decimal> {
+        if currency.is_jpy() {
+            return Some(Decimal::ONE);
+        }
+        let (rate_date, rate) = self.rates.get(currency)?.range(..=date).next_back()?;
+        ((date - *rate_date).num_days() <= MAX_RATE_AGE_DAYS).then_some(*rate)
+    }
+
+    // 取り込み済みのうち最新のレート。入金予定など将来の金額の換算に使う
+    pub fn latest_rate(&self, currency: &Currency) -> Option<Decimal> {
+        if currency.is_jpy() {
+            return Some(Decimal::ONE);
+        }
+        self.rates
+            .get(currency)?
+            .last_key_value()
+            .map(|(_, rate)| *rate)
+    }
+
+    // 円換算額。円未満は切り捨てる
+    pub fn convert(&self, money: &Money, date: NaiveDate) -> Option<Money> {
+        let rate = self.rate(&money.currency, date)?;
+        Some(Money::jpy((money.amount * rate).trunc()))
+    }
+}
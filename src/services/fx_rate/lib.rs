@@ -0,0 +1,105 @@
+use super::{
+    super::broker::lib::BrokerAdapter,
+    super::common::ParseDiagnostic,
+    super::csv::column_map::{ColumnMap, ColumnSpec},
+    super::store::lib::{HistoryQuery, ImportSummary, UserStore},
+    super::templete::{TableRenderer, TableView, TemplateManager, TemplateStruct},
+    fx_rate::FxRate,
+};
+use anyhow::Result;
+use csv::StringRecord;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use tera::Tera;
+
+// 為替レートのCSVは証券会社によらず「日付・通貨・TTM」の列を持つ形式とする。
+// TTMの代わりにTTSとTTBがあれば、その中間をTTMとする
+const FX_RATE_COLUMNS: &[ColumnSpec] = &[
+    ColumnSpec::required("rate_date", &["日付", "適用日", "Date"]),
+    ColumnSpec::required("currency", &["通貨", "通貨コード", "Currency"]),
+    ColumnSpec::optional("ttm", &["TTM", "仲値"]),
+    ColumnSpec::optional("tts", &["TTS"]),
+    ColumnSpec::optional("ttb", &["TTB"]),
+];
+
+// 外貨建ての配当を円に換算するための為替レートを取り込む
+pub struct FxRateManager {
+    template_struct: TemplateStruct,
+    fx_rates: RefCell<Vec<FxRate>>,
+}
+
+impl FxRateManager {
+    pub fn new() -> Self {
+        FxRateManager {
+            template_struct: TemplateStruct::new(),
+            fx_rates: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl TableRenderer for FxRateManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
+    }
+}
+
+impl TemplateManager for FxRateManager {
+    fn updates_annual_results(&self) -> bool {
+        true
+    }
+
+    fn is_broker_specific(&self) -> bool {
+        false
+    }
+
+    fn column_specs(&self, _broker: &dyn BrokerAdapter) -> &'static [ColumnSpec] {
+        FX_RATE_COLUMNS
+    }
+
+    fn process_records(&self, records: Vec<StringRecord>, columns: &ColumnMap) {
+        let mut diagnostics = self.template_struct().diagnostics.borrow_mut();
+        for record in records {
+            let fx_rate = FxRate::from_record(&record, columns, &mut diagnostics);
+            let (field, reason) = match (&fx_rate.rate_date, &fx_rate.currency, &fx_rate.ttm) {
+                (None, _, _) => ("rate_date", "Row skipped: missing date"),
+                (_, None, _) => ("currency", "Row skipped: missing currency"),
+                (_, _, None) => ("ttm", "Row skipped: missing TTM"),
+                _ => {
+                    self.fx_rates.borrow_mut().push(fx_rate);
+                    continue;
+                }
+            };
+            ParseDiagnostic::push_row_skipped(&mut diagnostics, &record, columns, field, reason);
+        }
+    }
+
+    fn save(&self, store: &UserStore) -> Result<ImportSummary> {
+        store.save_fx_rates(self.fx_rates.borrow().iter())
+    }
+
+    fn load(&self, store: &UserStore, query: &HistoryQuery) -> Result<()> {
+        self.fx_rates
+            .borrow_mut()
+            .extend(store.load_fx_rates(query)?);
+        Ok(())
+    }
+
+    fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let headers = self.generate_table_header(FxRate::new().get_all_fields());
+        let rows = self
+            .fx_rates
+            .borrow()
+            .iter()
+            .map(|fx_rate| self.generate_table_row(&fx_rate.get_all_fields()))
+            .collect();
+
+        let table = TableView { headers, rows };
+        self.render_table(tera, "partials/fx_rate_table.html", &table)
+    }
+
+    fn generate_json(&self) -> Result<Value> {
+        Ok(json!({
+            "rates": *self.fx_rates.borrow(),
+        }))
+    }
+}
@@ -0,0 +1,3 @@
+#[allow(clippy::module_inception)]
+pub mod fx_rate;
+pub mod lib;
@@ -0,0 +1,67 @@
+use chrono::{Datelike, Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+// 明細をまとめて小計を出す単位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Grouping {
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+    Security,
+    Account,
+}
+
+// 日付でまとめる場合は期間の初日、銘柄・口座でまとめる場合はその名前をキーにする
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(untagged)]
+pub enum GroupKey {
+    Date(NaiveDate),
+    Name(String),
+}
+
+impl Grouping {
+    // securityは「銘柄コード 銘柄名」のように表示用に組み立てたもの
+    pub fn key(&self, date: NaiveDate, security: &str, account: Option<&str>) -> GroupKey {
+        match self {
+            Grouping::Day => GroupKey::Date(date),
+            // 週は月曜日始まりとする
+            Grouping::Week => {
+                GroupKey::Date(date - Days::new(date.weekday().num_days_from_monday() as u64))
+            }
+            Grouping::Month => GroupKey::Date(date.with_day(1).unwrap()),
+            Grouping::Quarter => {
+                let month = (date.month0() / 3) * 3 + 1;
+                GroupKey::Date(NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap())
+            }
+            Grouping::Year => GroupKey::Date(NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap()),
+            Grouping::Security => GroupKey::Name(security.to_string()),
+            Grouping::Account => GroupKey::Name(account.unwrap_or("").to_string()),
+        }
+    }
+
+    pub fn label(&self, key: &GroupKey) -> String {
+        match key {
+            GroupKey::Date(date) => match self {
+                Grouping::Week => format!("{}週", date.format("%Y/%m/%d")),
+                Grouping::Month => date.format("%Y/%m").to_string(),
+                Grouping::Quarter => format!("{}/Q{}", date.year(), date.month0() / 3 + 1),
+                Grouping::Year => format!("{}年", date.year()),
+                _ => date.format("%Y/%m/%d").to_string(),
+            },
+            GroupKey::Name(name) if name.is_empty() => "(なし)".to_string(),
+            GroupKey::Name(name) => name.clone(),
+        }
+    }
+}
+
+// 銘柄でまとめるときのキー兼表示名
+pub fn security_label(security_code: Option<&str>, security_name: Option<&str>) -> String {
+    [security_code, security_name]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
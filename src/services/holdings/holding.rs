@@ -0,0 +1,236 @@
+use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::services::{
+    account::AccountType,
+    common::{rate, ParseDiagnostic, RecordReader},
+    csv::column_map::ColumnMap,
+    money::{Currency, Money},
+    trade_history::trade_history::FUND_PRICE_UNITS,
+};
+
+// 保有証券一覧の1銘柄（口座ごと）
+#[derive(Debug, Clone, Serialize)]
+pub struct Holding {
+    pub security_code: Option<String>,                    // 銘柄コード
+    pub security_name: Option<String>,                    // 銘柄名
+    pub account: Option<String>,                          // 口座
+    pub shares: Option<i32>,                              // 保有数量[株/口]
+    pub acquisition_price: Option<Money>,                 // 平均取得単価
+    pub current_price: Option<Money>,                     // 現在値
+    pub acquisition_amount: Option<Money>,                // 取得金額
+    pub valuation: Option<Money>,                         // 評価額
+    pub unrealized_profit_and_loss: Option<Money>,        // 評価損益
+    pub unrealized_profit_and_loss_rate: Option<Decimal>, // 評価損益率[%]
+}
+
+impl Holding {
+    pub fn new() -> Self {
+        Holding {
+            security_code: None,
+            security_name: None,
+            account: None,
+            shares: None,
+            acquisition_price: None,
+            current_price: None,
+            acquisition_amount: None,
+            valuation: None,
+            unrealized_profit_and_loss: None,
+            unrealized_profit_and_loss_rate: None,
+        }
+    }
+
+    pub fn from_record(
+        record: &StringRecord,
+        columns: &ColumnMap,
+        diagnostics: &mut Vec<ParseDiagnostic>,
+    ) -> Self {
+        let mut reader = RecordReader::new(record, columns, diagnostics);
+        let jpy = Currency::jpy();
+        Holding {
+            security_code: reader.string("security_code"),
+            security_name: reader.string("security_name"),
+            account: reader.string("account"),
+            shares: reader.int("shares"),
+            acquisition_price: reader.money("acquisition_price", &jpy),
+            current_price: reader.money("current_price", &jpy),
+            valuation: reader.money("valuation", &jpy),
+            unrealized_profit_and_loss: reader.money("unrealized_profit_and_loss", &jpy),
+            ..Self::new()
+        }
+        .with_valuation()
+    }
+
+    // 単価が何口あたりの価格か。約定履歴と同じく、銘柄コードのない銘柄は投資信託とみなす
+    pub fn price_units(&self) -> Decimal {
+        match self.security_code.as_deref().is_none_or(str::is_empty) {
+            true => FUND_PRICE_UNITS,
+            false => Decimal::ONE,
+        }
+    }
+
+    // CSVに評価額・評価損益があればそれを優先し、なければ数量と単価から計算する（円未満切り捨て）。
+    // 投資信託の単価は1万口あたりの価格
+    pub fn with_valuation(self) -> Self {
+        let amount = |money: &Option<Money>| money.as_ref().map(|m| m.amount);
+        let price_units = self.price_units();
+        let amount_of = |price: Option<Decimal>| {
+            price
+                .zip(self.shares)
+                .map(|(price, shares)| (price * Decimal::from(shares) / price_units).trunc())
+        };
+        let valuation = amount(&self.valuation).or_else(|| amount_of(amount(&self.current_price)));
+        let acquisition_amount = match (valuation, amount(&self.unrealized_profit_and_loss)) {
+            (Some(valuation), Some(unrealized)) => Some(valuation - unrealized),
+            _ => amount_of(amount(&self.acquisition_price)),
+        };
+        let unrealized = amount(&self.unrealized_profit_and_loss).or_else(|| {
+            valuation
+                .zip(acquisition_amount)
+                .map(|(valuation, acquisition_amount)| valuation - acquisition_amount)
+        });
+
+        Holding {
+            acquisition_amount: acquisition_amount.map(Money::jpy),
+            valuation: valuation.map(Money::jpy),
+            unrealized_profit_and_loss: unrealized.map(Money::jpy),
+            unrealized_profit_and_loss_rate: rate(unrealized, acquisition_amount),
+            ..self
+        }
+    }
+
+    pub fn account_type(&self) -> AccountType {
+        AccountType::parse(self.account.as_deref())
+    }
+
+    // 口座区分ごと・全体の合計。口座欄には合計の名前を入れる
+    pub fn new_total(label: &str, holdings: &[Holding]) -> Self {
+        let sum = |field: fn(&Holding) -> &Option<Money>| {
+            holdings
+                .iter()
+                .filter_map(|holding| field(holding).as_ref())
+                .map(|money| money.amount)
+                .sum::<Decimal>()
+        };
+        let acquisition_amount = sum(|holding| &holding.acquisition_amount);
+        let valuation = sum(|holding| &holding.valuation);
+        let unrealized = sum(|holding| &holding.unrealized_profit_and_loss);
+
+        Holding {
+            account: Some(label.to_string()),
+            acquisition_amount: Some(Money::jpy(acquisition_amount)),
+            valuation: Some(Money::jpy(valuation)),
+            unrealized_profit_and_loss: Some(Money::jpy(unrealized)),
+            unrealized_profit_and_loss_rate: rate(Some(unrealized), Some(acquisition_amount)),
+            ..Self::new()
+        }
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        let money = |m: &Option<Money>| m.as_ref().map(|m| m.to_string());
+        vec![
+            ("security_code".to_string(), self.security_code.clone()),
+            ("security_name".to_string(), self.security_name.clone()),
+            ("account".to_string(), self.account.clone()),
+            ("shares".to_string(), self.shares.map(|s| s.to_string())),
+            (
+                "acquisition_price".to_string(),
+                money(&self.acquisition_price),
+            ),
+            ("current_price".to_string(), money(&self.current_price)),
+            (
+                "acquisition_amount".to_string(),
+                money(&self.acquisition_amount),
+            ),
+            ("valuation".to_string(), money(&self.valuation)),
+            (
+                "unrealized_profit_and_loss".to_string(),
+                money(&self.unrealized_profit_and_loss),
+            ),
+            (
+                "unrealized_profit_and_loss_rate".to_string(),
+                self.unrealized_profit_and_loss_rate
+                    .map(|rate| rate.to_string()),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::csv::column_map::ColumnSpec;
+
+    const SPECS: &[ColumnSpec] = &[
+        ColumnSpec::optional("security_code", &["銘柄コード"]),
+        ColumnSpec::required("security_name", &["銘柄"]),
+        ColumnSpec::required("account", &["口座"]),
+        ColumnSpec::required("shares", &["保有数量"]),
+        ColumnSpec::required("acquisition_price", &["平均取得価額"]),
+        ColumnSpec::required("current_price", &["現在値"]),
+        ColumnSpec::optional("valuation", &["時価評価額"]),
+        ColumnSpec::optional("unrealized_profit_and_loss", &["評価損益"]),
+    ];
+
+    fn parse(values: Vec<&str>) -> Holding {
+        let headers = StringRecord::from(vec![
+            "銘柄コード",
+            "銘柄",
+            "口座",
+            "保有数量",
+            "平均取得価額",
+            "現在値",
+        ]);
+        let columns = ColumnMap::new(SPECS, &headers).unwrap();
+        let mut diagnostics = Vec::new();
+        let holding = Holding::from_record(&StringRecord::from(values), &columns, &mut diagnostics);
+        assert!(diagnostics.is_empty());
+        holding
+    }
+
+    fn amount(money: &Option<Money>) -> Option<Decimal> {
+        money.as_ref().map(|m| m.amount)
+    }
+
+    #[test]
+    fn fund_valuation_without_valuation_column_is_per_ten_thousand_units() {
+        let holding = parse(vec![
+            "",
+            "テストファンド",
+            "特定",
+            "123456",
+            "20000",
+            "25000",
+        ]);
+
+        // 123,456口 × 25,000円 / 10,000口 = 308,640円、取得金額は 246,912円
+        assert_eq!(amount(&holding.valuation), Some(Decimal::from(308_640)));
+        assert_eq!(
+            amount(&holding.acquisition_amount),
+            Some(Decimal::from(246_912))
+        );
+        assert_eq!(
+            amount(&holding.unrealized_profit_and_loss),
+            Some(Decimal::from(61_728))
+        );
+    }
+
+    #[test]
+    fn stock_valuation_without_valuation_column_is_per_share() {
+        let holding = parse(vec![
+            "7203",
+            "トヨタ自動車",
+            "特定",
+            "100",
+            "2500",
+            "3000.5",
+        ]);
+
+        assert_eq!(amount(&holding.valuation), Some(Decimal::from(300_050)));
+        assert_eq!(
+            amount(&holding.acquisition_amount),
+            Some(Decimal::from(250_000))
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use super::{
+    super::account::AccountType,
+    super::broker::lib::BrokerAdapter,
+    super::common::ParseDiagnostic,
+    super::csv::column_map::{ColumnMap, ColumnSpec},
+    super::store::lib::{HistoryQuery, ImportSummary, UserStore},
+    super::templete::{TableRenderer, TableView, TemplateManager, TemplateStruct},
+    holding::Holding,
+};
+use anyhow::{anyhow, Result};
+use csv::StringRecord;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use tera::Tera;
+
+// 保有証券一覧から口座ごとの評価損益を表示する
+pub struct HoldingsManager {
+    template_struct: TemplateStruct,
+    holdings: RefCell<Vec<Holding>>,
+}
+
+impl HoldingsManager {
+    pub fn new() -> Self {
+        HoldingsManager {
+            template_struct: TemplateStruct::new(),
+            holdings: RefCell::new(Vec::new()),
+        }
+    }
+
+    // 口座区分の表示順にまとめる
+    fn group_holdings(&self) -> Vec<(AccountType, Vec<Holding>)> {
+        let holdings = self.holdings.borrow();
+        AccountType::ALL
+            .into_iter()
+            .map(|account_type| {
+                let holdings = holdings
+                    .iter()
+                    .filter(|holding| holding.account_type() == account_type)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                (account_type, holdings)
+            })
+            .filter(|(_, holdings)| !holdings.is_empty())
+            .collect()
+    }
+
+    fn calculate_grand_total(&self) -> Holding {
+        Holding::new_total("合計", &self.holdings.borrow())
+    }
+}
+
+impl TableRenderer for HoldingsManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
+    }
+}
+
+impl TemplateManager for HoldingsManager {
+    fn column_specs(&self, broker: &dyn BrokerAdapter) -> &'static [ColumnSpec] {
+        broker.holdings_columns()
+    }
+
+    fn process_records(&self, records: Vec<StringRecord>, columns: &ColumnMap) {
+        let mut diagnostics = self.template_struct().diagnostics.borrow_mut();
+        for record in records {
+            let holding = Holding::from_record(&record, columns, &mut diagnostics);
+            if holding.shares.is_none() {
+                ParseDiagnostic::push_row_skipped(
+                    &mut diagnostics,
+                    &record,
+                    columns,
+                    "shares",
+                    "Row skipped: missing shares",
+                );
+                continue;
+            }
+            self.holdings.borrow_mut().push(holding);
+        }
+    }
+
+    fn save(&self, store: &UserStore) -> Result<ImportSummary> {
+        let broker = self
+            .template_struct
+            .broker
+            .get()
+            .ok_or_else(|| anyhow!("Broker of the imported holdings is unknown"))?;
+        store.replace_holdings(broker.id(), self.holdings.borrow().iter())
+    }
+
+    fn load(&self, store: &UserStore, query: &HistoryQuery) -> Result<()> {
+        self.holdings
+            .borrow_mut()
+            .extend(store.load_holdings(query)?);
+        Ok(())
+    }
+
+    fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let headers = self.generate_table_header(Holding::new().get_all_fields());
+
+        let mut rows = Vec::new();
+        for (account_type, holdings) in self.group_holdings() {
+            for holding in &holdings {
+                rows.push(self.generate_table_row(&holding.get_all_fields()));
+            }
+            let total = Holding::new_total(account_type.label(), &holdings);
+            rows.push(self.generate_table_row_with_class("group-total", &total.get_all_fields()));
+        }
+        rows.push(self.generate_table_row_with_class(
+            "grand-total",
+            &self.calculate_grand_total().get_all_fields(),
+        ));
+
+        let table = TableView { headers, rows };
+        self.render_table(tera, "partials/holdings_table.html", &table)
+    }
+
+    fn generate_json(&self) -> Result<Value> {
+        let groups = self
+            .group_holdings()
+            .into_iter()
+            .map(|(account_type, holdings)| {
+                json!({
+                    "account_type": account_type,
+                    "label": account_type.label(),
+                    "total": Holding::new_total(account_type.label(), &holdings),
+                    "rows": holdings,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(json!({
+            "groups": groups,
+            "grand_total": self.calculate_grand_total(),
+        }))
+    }
+}
@@ -0,0 +1,2 @@
+pub mod holding;
+pub mod lib;
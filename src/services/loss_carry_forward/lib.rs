@@ -0,0 +1,80 @@
+use super::{
+    super::store::lib::UserStore,
+    super::tax_return::lib::TaxReturnManager,
+    super::templete::{TableRenderer, TableView, TemplateStruct},
+    loss_carry_forward::{LossCarryForward, LossPiece},
+};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tera::{Context, Tera};
+
+// 確定申告の有無を切り替えるフォームの入力
+#[derive(Debug, Deserialize)]
+pub struct FilingForm {
+    pub year: i32,
+    pub filed: bool,
+}
+
+// 年ごとの損失の繰越控除と、年末時点で残っている繰越損失の内訳を表示する
+pub struct LossCarryForwardManager {
+    template_struct: TemplateStruct,
+    tax_return_manager: TaxReturnManager,
+}
+
+impl LossCarryForwardManager {
+    pub fn new() -> Self {
+        LossCarryForwardManager {
+            template_struct: TemplateStruct::new(),
+            tax_return_manager: TaxReturnManager::new(),
+        }
+    }
+
+    pub fn load(&self, store: &UserStore) -> Result<()> {
+        self.tax_return_manager.load(store)
+    }
+
+    pub fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let loss_carry_forwards = self.tax_return_manager.loss_carry_forwards();
+
+        let headers = self.generate_table_header(LossCarryForward::new().get_all_fields());
+        let rows = loss_carry_forwards
+            .iter()
+            .map(|carry_forward| {
+                let tr_class = if carry_forward.filed { "" } else { "not-filed" };
+                self.generate_table_row_with_class(tr_class, &carry_forward.get_all_fields())
+            })
+            .collect();
+        let table = TableView { headers, rows };
+
+        // 最新の年末時点で残っている損失を、控除期限の近い順に表示する
+        let headers = self.generate_table_header(LossPiece::new().get_all_fields());
+        let rows = loss_carry_forwards
+            .last()
+            .map(|carry_forward| {
+                carry_forward
+                    .remaining_pieces
+                    .iter()
+                    .map(|piece| self.generate_table_row(&piece.get_all_fields()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let pieces = TableView { headers, rows };
+
+        let mut context = Context::new();
+        context.insert("table", &table);
+        context.insert("pieces", &pieces);
+        context.insert("annual_results", &self.tax_return_manager.annual_results());
+        self.render_context(tera, "partials/loss_carry_forward_table.html", context)
+    }
+
+    pub fn generate_json(&self) -> Value {
+        json!({ "years": self.tax_return_manager.loss_carry_forwards() })
+    }
+}
+
+impl TableRenderer for LossCarryForwardManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
+    }
+}
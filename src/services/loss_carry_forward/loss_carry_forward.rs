@@ -0,0 +1,263 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+use crate::services::money::Money;
+
+// 譲渡損失を繰り越して控除できる年数
+pub const CARRY_FORWARD_YEARS: i32 = 3;
+
+// 年ごとの課税口座（特定・一般）の損益。ユーザーごとに保存し、申告の有無を記録する
+#[derive(Debug, Clone, Serialize)]
+pub struct AnnualResult {
+    pub year: i32,
+    pub transfer_income: Decimal, // 上場株式等の譲渡所得等（損失なら負）
+    pub dividend_income: Decimal, // 申告分離課税を選択した上場株式等の配当所得
+    pub filed: bool,              // 確定申告をしたか（しなければ損失を繰り越せない）
+}
+
+// 繰り越している損失のうち、発生した年ごとの残高
+#[derive(Debug, Clone, Serialize)]
+pub struct LossPiece {
+    pub origin_year: i32,              // 発生年
+    pub remaining_loss: Option<Money>, // 繰越残高
+    pub expiry_year: i32,              // 控除できる最後の年
+}
+
+impl LossPiece {
+    pub fn new() -> Self {
+        LossPiece {
+            origin_year: 0,
+            remaining_loss: None,
+            expiry_year: 0,
+        }
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        vec![
+            (
+                "origin_year".to_string(),
+                Some(self.origin_year.to_string()),
+            ),
+            (
+                "remaining_loss".to_string(),
+                self.remaining_loss.as_ref().map(|m| m.to_string()),
+            ),
+            (
+                "expiry_year".to_string(),
+                Some(self.expiry_year.to_string()),
+            ),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LossCarryForward {
+    pub year: i32,                        // 年
+    pub filed: bool,                      // 申告
+    pub transfer_income: Option<Money>,   // 譲渡所得等
+    pub dividend_income: Option<Money>,   // 配当所得
+    pub netted_income: Option<Money>,     // 損益通算後の所得
+    pub carried_in_loss: Option<Money>,   // 前年から繰り越された損失
+    pub expired_loss: Option<Money>,      // 控除期限を過ぎて切り捨てられた損失
+    pub used_loss: Option<Money>,         // 本年分で差し引く損失
+    pub new_loss: Option<Money>,          // 本年に発生した損失
+    pub carried_out_loss: Option<Money>,  // 翌年以後に繰り越される損失
+    pub taxable_income: Option<Money>,    // 繰越控除後の所得
+    pub remaining_pieces: Vec<LossPiece>, // 年末時点の発生年ごとの繰越残高
+}
+
+impl LossCarryForward {
+    pub fn new() -> Self {
+        LossCarryForward {
+            year: 0,
+            filed: false,
+            transfer_income: None,
+            dividend_income: None,
+            netted_income: None,
+            carried_in_loss: None,
+            expired_loss: None,
+            used_loss: None,
+            new_loss: None,
+            carried_out_loss: None,
+            taxable_income: None,
+            remaining_pieces: Vec::new(),
+        }
+    }
+
+    // 古い年の損失から順に控除する。申告しなかった年があると、それまでの繰越損失は失われる。
+    // 年の抜けは、取引がなくても申告した年として扱う
+    pub fn calculate(results: &[AnnualResult]) -> Vec<LossCarryForward> {
+        let (Some(first), Some(last)) = (results.first(), results.last()) else {
+            return Vec::new();
+        };
+
+        let mut pieces: VecDeque<(i32, Decimal)> = VecDeque::new();
+        let mut carry_forwards = Vec::new();
+        for year in first.year..=last.year {
+            let result = results.iter().find(|result| result.year == year);
+            let transfer_income = result.map_or(Decimal::ZERO, |r| r.transfer_income);
+            let dividend_income = result.map_or(Decimal::ZERO, |r| r.dividend_income);
+            let filed = result.is_none_or(|r| r.filed);
+
+            let carried_in: Decimal = pieces.iter().map(|(_, loss)| loss).sum();
+            let mut expired = Decimal::ZERO;
+            while let Some(&(origin_year, loss)) = pieces.front() {
+                if filed && origin_year + CARRY_FORWARD_YEARS >= year {
+                    break;
+                }
+                expired += loss;
+                pieces.pop_front();
+            }
+
+            // 同じ年の譲渡損失は配当所得と損益通算してから繰り越す
+            let netted_income = transfer_income + dividend_income;
+            let mut used = Decimal::ZERO;
+            let mut new_loss = Decimal::ZERO;
+            if netted_income > Decimal::ZERO {
+                while let Some((_, loss)) = pieces.front_mut() {
+                    let deduction = (*loss).min(netted_income - used);
+                    used += deduction;
+                    *loss -= deduction;
+                    if !loss.is_zero() {
+                        break;
+                    }
+                    pieces.pop_front();
+                }
+            } else if netted_income < Decimal::ZERO && filed {
+                new_loss = -netted_income;
+                pieces.push_back((year, new_loss));
+            }
+
+            carry_forwards.push(LossCarryForward {
+                year,
+                filed,
+                transfer_income: Some(Money::jpy(transfer_income)),
+                dividend_income: Some(Money::jpy(dividend_income)),
+                netted_income: Some(Money::jpy(netted_income)),
+                carried_in_loss: Some(Money::jpy(carried_in)),
+                expired_loss: Some(Money::jpy(expired)),
+                used_loss: Some(Money::jpy(used)),
+                new_loss: Some(Money::jpy(new_loss)),
+                carried_out_loss: Some(Money::jpy(pieces.iter().map(|(_, loss)| loss).sum())),
+                taxable_income: Some(Money::jpy((netted_income - used).max(Decimal::ZERO))),
+                remaining_pieces: pieces
+                    .iter()
+                    .map(|&(origin_year, loss)| LossPiece {
+                        origin_year,
+                        remaining_loss: Some(Money::jpy(loss)),
+                        expiry_year: origin_year + CARRY_FORWARD_YEARS,
+                    })
+                    .collect(),
+            });
+        }
+
+        carry_forwards
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        let money = |m: &Option<Money>| m.as_ref().map(|m| m.to_string());
+        vec![
+            ("year".to_string(), Some(self.year.to_string())),
+            (
+                "filed".to_string(),
+                Some(if self.filed { "済" } else { "未" }.to_string()),
+            ),
+            ("transfer_income".to_string(), money(&self.transfer_income)),
+            ("dividend_income".to_string(), money(&self.dividend_income)),
+            ("netted_income".to_string(), money(&self.netted_income)),
+            ("carried_in_loss".to_string(), money(&self.carried_in_loss)),
+            ("expired_loss".to_string(), money(&self.expired_loss)),
+            ("used_loss".to_string(), money(&self.used_loss)),
+            ("new_loss".to_string(), money(&self.new_loss)),
+            (
+                "carried_out_loss".to_string(),
+                money(&self.carried_out_loss),
+            ),
+            ("taxable_income".to_string(), money(&self.taxable_income)),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(year: i32, transfer_income: i64, filed: bool) -> AnnualResult {
+        AnnualResult {
+            year,
+            transfer_income: Decimal::from(transfer_income),
+            dividend_income: Decimal::ZERO,
+            filed,
+        }
+    }
+
+    fn amount(money: &Option<Money>) -> Decimal {
+        money.as_ref().map_or(Decimal::ZERO, |m| m.amount)
+    }
+
+    fn pieces(carry_forward: &LossCarryForward) -> Vec<(i32, Decimal)> {
+        carry_forward
+            .remaining_pieces
+            .iter()
+            .map(|piece| (piece.origin_year, amount(&piece.remaining_loss)))
+            .collect()
+    }
+
+    #[test]
+    fn deducts_oldest_losses_first_and_expires_them_after_three_years() {
+        let carry_forwards = LossCarryForward::calculate(&[
+            result(2020, -100_000, true),
+            result(2021, -50_000, true),
+            result(2023, 30_000, true),
+            result(2024, 10_000, true),
+            result(2025, 0, true),
+        ]);
+        let year = |year: i32| &carry_forwards[(year - 2020) as usize];
+
+        // 2022年は明細がなくても申告した年として扱い、損失を繰り越す
+        assert_eq!(
+            pieces(year(2022)),
+            vec![
+                (2020, Decimal::from(100_000)),
+                (2021, Decimal::from(50_000))
+            ]
+        );
+
+        // 2020年の損失から先に控除する
+        assert_eq!(amount(&year(2023).used_loss), Decimal::from(30_000));
+        assert_eq!(amount(&year(2023).taxable_income), Decimal::ZERO);
+        assert_eq!(
+            pieces(year(2023)),
+            vec![(2020, Decimal::from(70_000)), (2021, Decimal::from(50_000))]
+        );
+
+        // 2020年の損失は2023年までしか控除できない
+        assert_eq!(amount(&year(2024).expired_loss), Decimal::from(70_000));
+        assert_eq!(amount(&year(2024).used_loss), Decimal::from(10_000));
+        assert_eq!(pieces(year(2024)), vec![(2021, Decimal::from(40_000))]);
+
+        assert_eq!(amount(&year(2025).expired_loss), Decimal::from(40_000));
+        assert_eq!(amount(&year(2025).carried_out_loss), Decimal::ZERO);
+    }
+
+    #[test]
+    fn drops_carried_losses_after_an_unfiled_year() {
+        let carry_forwards = LossCarryForward::calculate(&[
+            result(2022, -100_000, true),
+            result(2023, -30_000, false),
+            result(2024, 50_000, true),
+        ]);
+
+        // 申告しなかった年は、それまでの繰越損失もその年の損失も繰り越せない
+        let unfiled = &carry_forwards[1];
+        assert_eq!(amount(&unfiled.carried_in_loss), Decimal::from(100_000));
+        assert_eq!(amount(&unfiled.expired_loss), Decimal::from(100_000));
+        assert_eq!(amount(&unfiled.new_loss), Decimal::ZERO);
+        assert!(unfiled.remaining_pieces.is_empty());
+
+        let next = &carry_forwards[2];
+        assert_eq!(amount(&next.used_loss), Decimal::ZERO);
+        assert_eq!(amount(&next.taxable_income), Decimal::from(50_000));
+    }
+}
@@ -1,6 +1,23 @@
+pub mod account;
+pub mod auth;
+pub mod broker;
 pub mod common;
 pub mod csv;
+pub mod dividend_calendar;
 pub mod dividend_list;
+pub mod dividend_summary;
 pub mod factroy;
+pub mod foreign_tax_credit;
+pub mod fx_rate;
+pub mod grouping;
+pub mod holdings;
+pub mod loss_carry_forward;
+pub mod money;
+pub mod netting;
+pub mod nisa_limit;
 pub mod profit_and_loss;
+pub mod store;
+pub mod tax;
+pub mod tax_return;
 pub mod templete;
+pub mod trade_history;
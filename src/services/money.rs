@@ -0,0 +1,62 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::fmt;
+
+// ISO 4217の通貨コード
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(transparent)]
+pub struct Currency(String);
+
+impl Currency {
+    pub fn jpy() -> Self {
+        Currency("JPY".to_string())
+    }
+
+    // 証券会社のCSVに出てくる「日本円」「USドル」などの表記を通貨コードに変換する
+    pub fn from_label(label: &str) -> Self {
+        let code = match label.trim() {
+            "" | "円" | "日本円" | "JPY" => "JPY",
+            "USドル" | "米ドル" | "ドル" | "USD" => "USD",
+            "香港ドル" | "HKD" => "HKD",
+            "ユーロ" | "EUR" => "EUR",
+            "英ポンド" | "GBP" => "GBP",
+            "豪ドル" | "AUD" => "AUD",
+            "シンガポールドル" | "SGD" => "SGD",
+            label => label,
+        };
+        Currency(code.to_string())
+    }
+
+    pub fn is_jpy(&self) -> bool {
+        self.0 == "JPY"
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// 通貨付きの固定小数点金額
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Money {
+    pub amount: Decimal,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: Decimal, currency: Currency) -> Self {
+        Money { amount, currency }
+    }
+
+    pub fn jpy(amount: Decimal) -> Self {
+        Money::new(amount, Currency::jpy())
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.amount.normalize())
+    }
+}
@@ -0,0 +1,130 @@
+use super::{
+    super::broker::lib::find_broker,
+    super::dividend_list::lib::DividendListManager,
+    super::profit_and_loss::lib::ProfitAndLossManager,
+    super::templete::{ReportOptions, TableRenderer, TableView, TemplateManager, TemplateStruct},
+    netting::Netting,
+};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use tera::Tera;
+
+// 配当金と実現損益の2つのCSVを読み込み、特定口座内の損益通算を年ごとに計算する
+pub struct NettingManager {
+    template_struct: TemplateStruct,
+    dividend_list_manager: DividendListManager,
+    profit_and_loss_manager: ProfitAndLossManager,
+}
+
+impl NettingManager {
+    // brokerを指定した場合は、配当金と実現損益のどちらのCSVもその証券会社の形式として読み込む
+    pub fn new(broker: Option<&str>) -> Result<Self> {
+        let manager = NettingManager {
+            template_struct: TemplateStruct::new(),
+            dividend_list_manager: DividendListManager::new(),
+            profit_and_loss_manager: ProfitAndLossManager::new(),
+        };
+        if let Some(broker) = broker {
+            let broker = find_broker(broker)?;
+            manager
+                .dividend_list_manager
+                .template_struct()
+                .broker
+                .set(Some(broker));
+            manager
+                .profit_and_loss_manager
+                .template_struct()
+                .broker
+                .set(Some(broker));
+        }
+        Ok(manager)
+    }
+
+    pub fn execute(
+        &self,
+        dividend_bytes: Vec<u8>,
+        profit_and_loss_bytes: Vec<u8>,
+        options: &ReportOptions,
+        tera: &Tera,
+    ) -> Result<String> {
+        self.import(dividend_bytes, profit_and_loss_bytes, options)?;
+        self.generate_html_table(tera)
+    }
+
+    pub fn execute_json(
+        &self,
+        dividend_bytes: Vec<u8>,
+        profit_and_loss_bytes: Vec<u8>,
+        options: &ReportOptions,
+    ) -> Result<Value> {
+        self.import(dividend_bytes, profit_and_loss_bytes, options)?;
+        Ok(json!({
+            "years": self.calculate_nettings(),
+            "warnings": &*self.template_struct.diagnostics.borrow(),
+        }))
+    }
+
+    fn import(
+        &self,
+        dividend_bytes: Vec<u8>,
+        profit_and_loss_bytes: Vec<u8>,
+        options: &ReportOptions,
+    ) -> Result<()> {
+        self.dividend_list_manager.import(dividend_bytes, options)?;
+        self.profit_and_loss_manager
+            .import(profit_and_loss_bytes, options)?;
+
+        // 両方のCSVの警告をまとめて表示する
+        let mut diagnostics = self.template_struct.diagnostics.borrow_mut();
+        for manager in [
+            &self.dividend_list_manager as &dyn TemplateManager,
+            &self.profit_and_loss_manager,
+        ] {
+            diagnostics.extend(
+                manager
+                    .template_struct()
+                    .diagnostics
+                    .borrow()
+                    .iter()
+                    .cloned(),
+            );
+        }
+        Ok(())
+    }
+
+    fn calculate_nettings(&self) -> Vec<Netting> {
+        let dividends = self.dividend_list_manager.annual_specific_account_totals();
+        let profit_and_losses = self
+            .profit_and_loss_manager
+            .annual_specific_account_totals();
+
+        let years = dividends
+            .keys()
+            .chain(profit_and_losses.keys())
+            .copied()
+            .collect::<BTreeSet<_>>();
+        years
+            .into_iter()
+            .map(|year| Netting::new(year, dividends.get(&year), profit_and_losses.get(&year)))
+            .collect()
+    }
+
+    fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let nettings = self.calculate_nettings();
+        let headers = self.generate_table_header(Netting::new(0, None, None).get_all_fields());
+        let rows = nettings
+            .iter()
+            .map(|netting| self.generate_table_row(&netting.get_all_fields()))
+            .collect();
+
+        let table = TableView { headers, rows };
+        self.render_table(tera, "partials/netting_table.html", &table)
+    }
+}
+
+impl TableRenderer for NettingManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
+    }
+}
@@ -0,0 +1,81 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::services::{
+    dividend_list::dividend_list::DividendList, money::Money,
+    profit_and_loss::profit_and_loss::ProfitAndLoss, tax::Withholding,
+};
+
+// 特定口座内での配当と譲渡損失の損益通算（年単位）
+#[derive(Debug, Clone, Serialize)]
+pub struct Netting {
+    pub year: i32,                                     // 年
+    pub total_dividends_before_tax: Option<Money>,     // 配当・分配金合計(税引前)
+    pub total_taxes: Option<Money>,                    // 配当の源泉徴収税額
+    pub total_realized_profit_and_loss: Option<Money>, // 実現損益合計
+    pub cumulative_withholding_tax: Option<Money>,     // 譲渡益の源泉徴収税額
+    pub netted_income: Option<Money>,                  // 損益通算後の所得
+    pub netted_withholding_tax: Option<Money>,         // 損益通算後の税額
+    pub expected_refund: Option<Money>,                // 還付見込額
+}
+
+impl Netting {
+    pub fn new(
+        year: i32,
+        dividend: Option<&DividendList>,
+        profit_and_loss: Option<&ProfitAndLoss>,
+    ) -> Self {
+        let amount = |money: Option<&Money>| money.map_or(Decimal::ZERO, |m| m.amount);
+
+        let dividends = amount(dividend.and_then(|d| d.total_dividends_before_tax.as_ref()));
+        let dividend_taxes = amount(dividend.and_then(|d| d.total_taxes.as_ref()));
+        let realized =
+            amount(profit_and_loss.and_then(|p| p.total_realized_profit_and_loss.as_ref()));
+        let realized_taxes =
+            amount(profit_and_loss.and_then(|p| p.cumulative_withholding_tax.as_ref()));
+
+        // 年間の配当と損益を合算した所得に対して改めて税額を計算し、
+        // 既に徴収された額との差を還付見込額とする
+        let netted_income = dividends + realized;
+        let netted_withholding_tax = Withholding::calculate(netted_income).total();
+        let expected_refund =
+            (dividend_taxes + realized_taxes - netted_withholding_tax).max(Decimal::ZERO);
+
+        Netting {
+            year,
+            total_dividends_before_tax: Some(Money::jpy(dividends)),
+            total_taxes: Some(Money::jpy(dividend_taxes)),
+            total_realized_profit_and_loss: Some(Money::jpy(realized)),
+            cumulative_withholding_tax: Some(Money::jpy(realized_taxes)),
+            netted_income: Some(Money::jpy(netted_income.max(Decimal::ZERO))),
+            netted_withholding_tax: Some(Money::jpy(netted_withholding_tax)),
+            expected_refund: Some(Money::jpy(expected_refund)),
+        }
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        let money = |m: &Option<Money>| m.as_ref().map(|m| m.to_string());
+        vec![
+            ("year".to_string(), Some(self.year.to_string())),
+            (
+                "total_dividends_before_tax".to_string(),
+                money(&self.total_dividends_before_tax),
+            ),
+            ("total_taxes".to_string(), money(&self.total_taxes)),
+            (
+                "total_realized_profit_and_loss".to_string(),
+                money(&self.total_realized_profit_and_loss),
+            ),
+            (
+                "cumulative_withholding_tax".to_string(),
+                money(&self.cumulative_withholding_tax),
+            ),
+            ("netted_income".to_string(), money(&self.netted_income)),
+            (
+                "netted_withholding_tax".to_string(),
+                money(&self.netted_withholding_tax),
+            ),
+            ("expected_refund".to_string(), money(&self.expected_refund)),
+        ]
+    }
+}
@@ -0,0 +1,85 @@
+use super::{
+    super::store::lib::{HistoryQuery, UserStore},
+    super::templete::{TableRenderer, TableView, TemplateStruct},
+    super::trade_history::trade_history::TradeHistory,
+    nisa_limit::NisaUsage,
+};
+use anyhow::Result;
+use chrono::{Datelike, Local};
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use tera::Tera;
+
+// 保存済みの約定履歴からNISAの投資枠の利用状況を年ごとに集計する。
+// 約定履歴の取り込みは取得価額の照合と共通で、ここでは読み込むだけ
+pub struct NisaLimitManager {
+    template_struct: TemplateStruct,
+    trade_history: RefCell<Vec<TradeHistory>>,
+}
+
+impl NisaLimitManager {
+    pub fn new() -> Self {
+        NisaLimitManager {
+            template_struct: TemplateStruct::new(),
+            trade_history: RefCell::new(Vec::new()),
+        }
+    }
+
+    // 生涯投資枠は過去の約定も含めて計算するため、保存済みの約定をすべて読む
+    pub fn load(&self, store: &UserStore) -> Result<()> {
+        self.trade_history.borrow_mut().extend(
+            store
+                .load_trade_history(&HistoryQuery::default())?
+                .into_iter()
+                .filter(|trade| trade.trade_date.is_some()),
+        );
+        Ok(())
+    }
+
+    // 今年の残りの枠が分かるよう、約定がなくても今年までの行を出す
+    fn calculate_usages(&self) -> Vec<NisaUsage> {
+        let trade_history = self.trade_history.borrow();
+        let last_year = trade_history
+            .iter()
+            .filter_map(|trade| trade.trade_date)
+            .map(|date| date.year())
+            .max()
+            .unwrap_or(i32::MIN);
+        NisaUsage::calculate(&trade_history, last_year.max(Local::now().year()))
+    }
+
+    pub fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let usages = self.calculate_usages();
+        let headers = match usages.first() {
+            Some(usage) => self.generate_table_header(usage.get_all_fields()),
+            None => Vec::new(),
+        };
+        let rows = usages
+            .iter()
+            .map(|usage| {
+                let tr_class = match (usage.is_total(), usage.is_over_limit()) {
+                    (true, true) => "group-total over-limit",
+                    (true, false) => "group-total",
+                    (false, true) => "over-limit",
+                    (false, false) => "",
+                };
+                self.generate_table_row_with_class(tr_class, &usage.get_all_fields())
+            })
+            .collect();
+
+        let table = TableView { headers, rows };
+        self.render_table(tera, "partials/nisa_limit_table.html", &table)
+    }
+
+    pub fn generate_json(&self) -> Value {
+        json!({
+            "usages": self.calculate_usages(),
+        })
+    }
+}
+
+impl TableRenderer for NisaLimitManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
+    }
+}
@@ -0,0 +1,3 @@
+pub mod lib;
+#[allow(clippy::module_inception)]
+pub mod nisa_limit;
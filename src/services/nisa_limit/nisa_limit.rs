@@ -0,0 +1,337 @@
+use chrono::Datelike;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::services::{
+    account::AccountType,
+    money::Money,
+    trade_history::trade_history::{TradeHistory, TradeSide},
+};
+
+// 2024年からの新しいNISA制度
+pub const NISA_START_YEAR: i32 = 2024;
+// 生涯投資枠（簿価）。成長投資枠はこのうち1,200万円まで
+const LIFETIME_LIMIT: Decimal = Decimal::from_parts(18_000_000, 0, 0, false, 0);
+
+// 新NISAの投資枠
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NisaFrame {
+    Growth,    // 成長投資枠
+    Tsumitate, // つみたて投資枠
+}
+
+impl NisaFrame {
+    pub const ALL: [NisaFrame; 2] = [NisaFrame::Growth, NisaFrame::Tsumitate];
+
+    // 旧NISA・ジュニアNISAは新NISAの投資枠を使わない
+    pub fn from_account_type(account_type: AccountType) -> Option<Self> {
+        match account_type {
+            AccountType::NisaGrowth => Some(NisaFrame::Growth),
+            AccountType::NisaTsumitate => Some(NisaFrame::Tsumitate),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NisaFrame::Growth => "成長投資枠",
+            NisaFrame::Tsumitate => "つみたて投資枠",
+        }
+    }
+
+    fn annual_limit(&self) -> Decimal {
+        match self {
+            NisaFrame::Growth => Decimal::from(2_400_000),
+            NisaFrame::Tsumitate => Decimal::from(1_200_000),
+        }
+    }
+
+    fn lifetime_limit(&self) -> Decimal {
+        match self {
+            NisaFrame::Growth => Decimal::from(12_000_000),
+            NisaFrame::Tsumitate => LIFETIME_LIMIT,
+        }
+    }
+}
+
+// 投資枠ごとの年間の買付額と、売却した分の簿価
+#[derive(Debug, Default, Clone, Copy)]
+struct AnnualFlow {
+    bought: Decimal,
+    sold_book_value: Decimal,
+}
+
+// 年ごと・投資枠ごとの利用状況
+#[derive(Debug, Clone, Serialize)]
+pub struct NisaUsage {
+    pub year: i32,                 // 年
+    pub frame: String,             // 投資枠
+    pub annual_limit: Money,       // 年間投資枠
+    pub annual_used: Money,        // 年間の買付額
+    pub annual_remaining: Money,   // 年間投資枠の残り
+    pub restored_capacity: Money,  // 前年の売却で復活した生涯投資枠
+    pub lifetime_limit: Money,     // 生涯投資枠
+    pub lifetime_used: Money,      // 生涯投資枠の利用額（簿価）
+    pub lifetime_remaining: Money, // 生涯投資枠の残り
+}
+
+impl NisaUsage {
+    // 年間投資枠は買付額で消費し、売却しても戻らない。生涯投資枠は売却した分の簿価が翌年に復活する。
+    // 売却した分の簿価は、投資枠・銘柄ごとの平均取得価額から求める。投資信託の解約も売却として扱う
+    pub fn calculate(trade_history: &[TradeHistory], through_year: i32) -> Vec<Self> {
+        let mut flows: BTreeMap<(i32, NisaFrame), AnnualFlow> = BTreeMap::new();
+        let mut holdings: HashMap<(NisaFrame, String), (Decimal, Decimal)> = HashMap::new();
+
+        let mut trades = trade_history
+            .iter()
+            .filter(|trade| {
+                trade
+                    .trade_date
+                    .is_some_and(|date| date.year() >= NISA_START_YEAR)
+            })
+            .filter_map(|trade| {
+                let frame = NisaFrame::from_account_type(trade.account_type())?;
+                Some((trade, frame, trade.side()?))
+            })
+            .collect::<Vec<_>>();
+        trades.sort_by_key(|(trade, _, _)| trade.trade_date);
+
+        let mut first_year = through_year;
+        for (trade, frame, side) in trades {
+            let year = trade.trade_date.map_or(through_year, |date| date.year());
+            first_year = first_year.min(year);
+            let shares = Decimal::from(trade.shares.unwrap_or_default());
+            let amount = trade.trade_amount().unwrap_or_default();
+            let flow = flows.entry((year, frame)).or_default();
+            let (held_shares, book_value) =
+                holdings.entry((frame, trade.security_key())).or_default();
+            match side {
+                TradeSide::Buy => {
+                    flow.bought += amount;
+                    *held_shares += shares;
+                    *book_value += amount;
+                }
+                TradeSide::Sell if held_shares.is_sign_positive() && !held_shares.is_zero() => {
+                    let sold_shares = shares.min(*held_shares);
+                    let sold_book_value = *book_value * sold_shares / *held_shares;
+                    flow.sold_book_value += sold_book_value;
+                    *held_shares -= sold_shares;
+                    *book_value -= sold_book_value;
+                }
+                TradeSide::Sell => {}
+            }
+        }
+
+        let mut usages = Vec::new();
+        let mut bought_to_date: HashMap<NisaFrame, Decimal> = HashMap::new();
+        let mut restored_to_date: HashMap<NisaFrame, Decimal> = HashMap::new();
+        for year in first_year..=through_year {
+            let mut frame_usages = Vec::new();
+            for frame in NisaFrame::ALL {
+                let flow = flows.get(&(year, frame)).copied().unwrap_or_default();
+                let restored = flows
+                    .get(&(year - 1, frame))
+                    .map_or(Decimal::ZERO, |flow| flow.sold_book_value);
+                let bought_to_date = bought_to_date.entry(frame).or_default();
+                let restored_to_date = restored_to_date.entry(frame).or_default();
+                *bought_to_date += flow.bought;
+                *restored_to_date += restored;
+                frame_usages.push((
+                    frame,
+                    flow.bought,
+                    restored,
+                    *bought_to_date - *restored_to_date,
+                ));
+            }
+
+            let total_used = frame_usages
+                .iter()
+                .map(|(_, _, _, lifetime_used)| *lifetime_used)
+                .sum::<Decimal>();
+            let total_remaining = LIFETIME_LIMIT - total_used;
+            let (mut annual_used, mut restored) = (Decimal::ZERO, Decimal::ZERO);
+            for (frame, bought, frame_restored, lifetime_used) in frame_usages {
+                annual_used += bought;
+                restored += frame_restored;
+                // 成長投資枠は自身の上限と、全体の生涯投資枠の残りの小さい方まで使える
+                let lifetime_remaining =
+                    (frame.lifetime_limit() - lifetime_used).min(total_remaining);
+                usages.push(Self::new(
+                    year,
+                    frame.label(),
+                    (frame.annual_limit(), bought),
+                    frame_restored,
+                    (frame.lifetime_limit(), lifetime_used, lifetime_remaining),
+                ));
+            }
+            let annual_limit = NisaFrame::ALL
+                .iter()
+                .map(|frame| frame.annual_limit())
+                .sum();
+            usages.push(Self::new(
+                year,
+                "合計",
+                (annual_limit, annual_used),
+                restored,
+                (LIFETIME_LIMIT, total_used, total_remaining),
+            ));
+        }
+
+        usages
+    }
+
+    fn new(
+        year: i32,
+        frame: &str,
+        (annual_limit, annual_used): (Decimal, Decimal),
+        restored_capacity: Decimal,
+        (lifetime_limit, lifetime_used, lifetime_remaining): (Decimal, Decimal, Decimal),
+    ) -> Self {
+        NisaUsage {
+            year,
+            frame: frame.to_string(),
+            annual_limit: Money::jpy(annual_limit),
+            annual_used: Money::jpy(annual_used),
+            annual_remaining: Money::jpy(annual_limit - annual_used),
+            restored_capacity: Money::jpy(restored_capacity),
+            lifetime_limit: Money::jpy(lifetime_limit),
+            lifetime_used: Money::jpy(lifetime_used),
+            lifetime_remaining: Money::jpy(lifetime_remaining),
+        }
+    }
+
+    pub fn is_total(&self) -> bool {
+        self.frame == "合計"
+    }
+
+    // 枠を超えて買い付けている（取込漏れや口座区分の判定誤りの可能性がある）
+    pub fn is_over_limit(&self) -> bool {
+        self.annual_remaining.amount.is_sign_negative() && !self.annual_remaining.amount.is_zero()
+            || self.lifetime_remaining.amount.is_sign_negative()
+                && !self.lifetime_remaining.amount.is_zero()
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        vec![
+            ("year".to_string(), Some(self.year.to_string())),
+            ("frame".to_string(), Some(self.frame.clone())),
+            (
+                "annual_limit".to_string(),
+                Some(self.annual_limit.to_string()),
+            ),
+            (
+                "annual_used".to_string(),
+                Some(self.annual_used.to_string()),
+            ),
+            (
+                "annual_remaining".to_string(),
+                Some(self.annual_remaining.to_string()),
+            ),
+            (
+                "restored_capacity".to_string(),
+                Some(self.restored_capacity.to_string()),
+            ),
+            (
+                "lifetime_limit".to_string(),
+                Some(self.lifetime_limit.to_string()),
+            ),
+            (
+                "lifetime_used".to_string(),
+                Some(self.lifetime_used.to_string()),
+            ),
+            (
+                "lifetime_remaining".to_string(),
+                Some(self.lifetime_remaining.to_string()),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn trade(year: i32, account: &str, trade_type: &str, shares: i32, amount: i64) -> TradeHistory {
+        TradeHistory {
+            trade_date: NaiveDate::from_ymd_opt(year, 3, 1),
+            security_name: Some(format!("{account}の銘柄")),
+            account: Some(account.to_string()),
+            trade_type: Some(trade_type.to_string()),
+            shares: Some(shares),
+            settlement_amount: Some(Money::jpy(Decimal::from(amount))),
+            ..TradeHistory::new()
+        }
+    }
+
+    fn usage<'a>(usages: &'a [NisaUsage], year: i32, frame: &str) -> &'a NisaUsage {
+        usages
+            .iter()
+            .find(|usage| usage.year == year && usage.frame == frame)
+            .unwrap()
+    }
+
+    fn yen(amount: i64) -> Decimal {
+        Decimal::from(amount)
+    }
+
+    #[test]
+    fn fund_redemption_frees_lifetime_capacity_the_next_year() {
+        let trades = [
+            trade(2024, "NISA(つ)", "積立買付", 600_000, 1_200_000),
+            trade(2025, "NISA(つ)", "解約", 300_000, 700_000),
+        ];
+        let usages = NisaUsage::calculate(&trades, 2026);
+
+        // 解約した年はまだ復活しない
+        let redeemed = usage(&usages, 2025, "つみたて投資枠");
+        assert_eq!(redeemed.restored_capacity.amount, Decimal::ZERO);
+        assert_eq!(redeemed.lifetime_used.amount, yen(1_200_000));
+
+        // 翌年に簿価60万円が復活する
+        let next_year = usage(&usages, 2026, "つみたて投資枠");
+        assert_eq!(next_year.restored_capacity.amount, yen(600_000));
+        assert_eq!(next_year.lifetime_used.amount, yen(600_000));
+        assert_eq!(next_year.lifetime_remaining.amount, yen(17_400_000));
+    }
+
+    #[test]
+    fn growth_frame_is_capped_by_its_own_limit_and_the_overall_limit() {
+        let mut trades = Vec::new();
+        for year in 2024..=2028 {
+            trades.push(trade(year, "NISA(成)", "株式現物買", 100, 2_000_000));
+        }
+        for year in 2024..=2029 {
+            trades.push(trade(year, "NISA(つ)", "積立買付", 100, 1_200_000));
+        }
+        let usages = NisaUsage::calculate(&trades, 2029);
+
+        // 2028年: 成長投資枠は1,000万円使用で、自身の上限1,200万円まで残り200万円
+        let growth = usage(&usages, 2028, "成長投資枠");
+        assert_eq!(growth.lifetime_remaining.amount, yen(2_000_000));
+
+        // 2029年: 全体で1,720万円使用のため、成長投資枠の残りも全体の残り80万円までになる
+        let growth = usage(&usages, 2029, "成長投資枠");
+        assert_eq!(growth.lifetime_used.amount, yen(10_000_000));
+        assert_eq!(growth.lifetime_remaining.amount, yen(800_000));
+        let total = usage(&usages, 2029, "合計");
+        assert_eq!(total.lifetime_used.amount, yen(17_200_000));
+        assert_eq!(total.lifetime_remaining.amount, yen(800_000));
+    }
+
+    #[test]
+    fn growth_frame_stops_at_twelve_million_while_overall_capacity_remains() {
+        let trades = (2024..=2028)
+            .map(|year| trade(year, "NISA(成)", "株式現物買", 100, 2_400_000))
+            .collect::<Vec<_>>();
+        let usages = NisaUsage::calculate(&trades, 2028);
+
+        let growth = usage(&usages, 2028, "成長投資枠");
+        assert_eq!(growth.lifetime_used.amount, yen(12_000_000));
+        assert_eq!(growth.lifetime_remaining.amount, Decimal::ZERO);
+        assert!(!growth.is_over_limit());
+        let tsumitate = usage(&usages, 2028, "つみたて投資枠");
+        assert_eq!(tsumitate.lifetime_remaining.amount, yen(6_000_000));
+    }
+}
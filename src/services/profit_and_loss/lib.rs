@@ -1,79 +1,468 @@
 use super::{
-    super::templete::{TemplateManager, TemplateStruct},
+    super::account::{AccountTotals, AccountType},
+    super::broker::lib::BrokerAdapter,
+    super::common::ParseDiagnostic,
+    super::csv::column_map::{ColumnMap, ColumnSpec},
+    super::grouping::{self, GroupKey, Grouping},
+    super::money::Money,
+    super::store::lib::{HistoryQuery, ImportSummary, UserStore},
+    super::tax::Withholding,
+    super::templete::{RowView, TableRenderer, TableView, TemplateManager, TemplateStruct},
     profit_and_loss::ProfitAndLoss,
 };
 use anyhow::Result;
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
 use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
 use std::{cell::RefCell, collections::BTreeMap};
+use tera::Tera;
 
 pub struct ProfitAndLossManager {
-    _template_struct: TemplateStruct,
+    template_struct: TemplateStruct,
     profit_and_loss_map: RefCell<BTreeMap<NaiveDate, Vec<ProfitAndLoss>>>,
 }
 
 impl ProfitAndLossManager {
     pub fn new() -> Self {
         ProfitAndLossManager {
-            _template_struct: TemplateStruct::new(),
+            template_struct: TemplateStruct::new(),
             profit_and_loss_map: RefCell::new(BTreeMap::new()),
         }
     }
 
-    fn generate_table_tbody(&self, table: &mut String, profit_and_loss_list: &[ProfitAndLoss]) {
-        let mut specific_account_total = 0;
-        let mut nisa_account_total = 0;
+    fn insert(&self, trade_date: NaiveDate, profit_and_loss: ProfitAndLoss) {
+        self.profit_and_loss_map
+            .borrow_mut()
+            .entry(trade_date)
+            .or_default()
+            .push(profit_and_loss);
+    }
 
+    fn sum_by_account<'a>(
+        &self,
+        profit_and_loss_list: impl IntoIterator<Item = &'a ProfitAndLoss>,
+    ) -> AccountTotals {
+        let mut totals = AccountTotals::default();
         for profit_and_loss in profit_and_loss_list {
-            table.push_str(&self.generate_table_row(&profit_and_loss.get_all_fields()));
-
-            if let (Some(account), Some(realized_profit_and_loss)) = (
-                profit_and_loss.account.as_deref(),
-                profit_and_loss.realized_profit_and_loss,
-            ) {
-                if account.contains("特定") {
-                    specific_account_total += realized_profit_and_loss;
-                } else {
-                    nisa_account_total += realized_profit_and_loss;
-                }
+            if let Some(realized_profit_and_loss) = &profit_and_loss.realized_profit_and_loss {
+                totals.add(
+                    profit_and_loss.account_type(),
+                    realized_profit_and_loss.amount,
+                );
             }
         }
+        totals
+    }
 
-        let total = (specific_account_total, nisa_account_total);
-        let profit_and_loss = ProfitAndLoss::new_total_realized_profit_and_loss(total);
+    // 未指定なら約定日ごとにまとめる
+    fn grouping(&self) -> Grouping {
+        self.template_struct.grouping.get().unwrap_or(Grouping::Day)
+    }
 
-        table.push_str(
-            &self.generate_table_row_with_class("group-total", &profit_and_loss.get_all_fields()),
-        );
+    // 約定日順に振り分けるので、グループ内の明細も約定日順になる
+    fn group_profit_and_loss(&self) -> BTreeMap<GroupKey, Vec<ProfitAndLoss>> {
+        let grouping = self.grouping();
+        let mut groups: BTreeMap<GroupKey, Vec<ProfitAndLoss>> = BTreeMap::new();
+        for (trade_date, profit_and_loss_list) in self.profit_and_loss_map.borrow().iter() {
+            for profit_and_loss in profit_and_loss_list {
+                let security = grouping::security_label(
+                    profit_and_loss.security_code.as_deref(),
+                    profit_and_loss.security_name.as_deref(),
+                );
+                let key = grouping.key(*trade_date, &security, profit_and_loss.account.as_deref());
+                groups.entry(key).or_default().push(profit_and_loss.clone());
+            }
+        }
+        groups
+    }
+
+    // 日付でまとめる場合は、年ごとに特定口座の損益を累計しながら日付順に各グループの合計を計算する。
+    // 年をまたぐ週は、明細ごとの約定日の年で分けて累計する
+    fn calculate_group_totals(
+        &self,
+        groups: &BTreeMap<GroupKey, Vec<ProfitAndLoss>>,
+    ) -> BTreeMap<GroupKey, ProfitAndLoss> {
+        let mut year_to_date: BTreeMap<i32, Decimal> = BTreeMap::new();
+        let mut totals = BTreeMap::new();
+
+        for (key, profit_and_loss_list) in groups {
+            let group_total = match key {
+                GroupKey::Date(_) => {
+                    let mut annual_lists: BTreeMap<i32, Vec<&ProfitAndLoss>> = BTreeMap::new();
+                    for profit_and_loss in profit_and_loss_list {
+                        if let Some(trade_date) = profit_and_loss.trade_date {
+                            annual_lists
+                                .entry(trade_date.year())
+                                .or_default()
+                                .push(profit_and_loss);
+                        }
+                    }
+                    let annual_totals = annual_lists
+                        .into_iter()
+                        .map(|(year, profit_and_loss_list)| {
+                            let total = self.sum_by_account(profit_and_loss_list);
+                            let year_to_date = year_to_date.entry(year).or_default();
+                            let annual_total = (total, *year_to_date);
+                            *year_to_date += total.specific;
+                            annual_total
+                        })
+                        .collect::<Vec<_>>();
+                    ProfitAndLoss::new_year_to_date_total(&annual_totals)
+                }
+                GroupKey::Name(_) => {
+                    ProfitAndLoss::new_group_total(self.sum_by_account(profit_and_loss_list))
+                }
+            };
+            totals.insert(key.clone(), group_total);
+        }
+
+        totals
+    }
+
+    fn calculate_grand_total(&self) -> ProfitAndLoss {
+        let total = self.sum_by_account(self.profit_and_loss_map.borrow().values().flatten());
+        ProfitAndLoss {
+            account: Some("合計".to_string()),
+            ..ProfitAndLoss::new_grand_total(total, self.calculate_withholding())
+        }
+    }
+
+    // 源泉徴収税額の合計。年ごとの特定口座の損益に対する税額を足し合わせる
+    fn calculate_withholding(&self) -> Withholding {
+        self.annual_specific_account_totals().values().fold(
+            Withholding::default(),
+            |sum, annual_total| Withholding {
+                income_tax: sum.income_tax + amount(&annual_total.income_tax),
+                resident_tax: sum.resident_tax + amount(&annual_total.resident_tax),
+            },
+        )
+    }
 
-        table.push_str("</tr>");
+    // 口座区分ごとの合計。源泉徴収があるのは特定口座だけなので、税額は特定口座の行にのみ出す
+    fn calculate_account_type_totals(&self) -> Vec<(AccountType, ProfitAndLoss)> {
+        let profit_and_loss_map = self.profit_and_loss_map.borrow();
+        AccountType::ALL
+            .into_iter()
+            .filter_map(|account_type| {
+                let profit_and_loss_list = profit_and_loss_map
+                    .values()
+                    .flatten()
+                    .filter(|profit_and_loss| {
+                        profit_and_loss.account_type() == account_type
+                            && profit_and_loss.realized_profit_and_loss.is_some()
+                    })
+                    .collect::<Vec<_>>();
+                if profit_and_loss_list.is_empty() {
+                    return None;
+                }
+
+                let total = self.sum_by_account(profit_and_loss_list);
+                let account_type_total = match account_type {
+                    AccountType::Specific => {
+                        ProfitAndLoss::new_grand_total(total, self.calculate_withholding())
+                    }
+                    _ => ProfitAndLoss::new_group_total(total),
+                };
+                let account_type_total = ProfitAndLoss {
+                    account: Some(account_type.label().to_string()),
+                    ..account_type_total
+                };
+                Some((account_type, account_type_total))
+            })
+            .collect()
+    }
+
+    // 年間の特定口座の損益と、それに対する源泉徴収税額
+    pub fn annual_specific_account_totals(&self) -> BTreeMap<i32, ProfitAndLoss> {
+        let mut annual_totals: BTreeMap<i32, Decimal> = BTreeMap::new();
+        for (date, profit_and_loss_list) in self.profit_and_loss_map.borrow().iter() {
+            let totals = self.sum_by_account(profit_and_loss_list);
+            *annual_totals.entry(date.year()).or_default() += totals.specific;
+        }
+
+        annual_totals
+            .into_iter()
+            .map(|(year, specific)| {
+                let total = AccountTotals {
+                    specific,
+                    ..AccountTotals::default()
+                };
+                let total = ProfitAndLoss::new_total_realized_profit_and_loss(total, Decimal::ZERO);
+                (year, total)
+            })
+            .collect()
+    }
+
+    // 一般口座の行は、源泉徴収されず申告が必要なことが分かるように印を付ける
+    fn generate_account_row(
+        &self,
+        tr_class: &str,
+        account_type: AccountType,
+        values: &[(String, Option<String>)],
+    ) -> RowView {
+        let tr_class = match account_type.requires_self_filing() {
+            true => format!("{tr_class} self-filing").trim().to_string(),
+            false => tr_class.to_string(),
+        };
+        self.generate_table_row_with_class(&tr_class, values)
+    }
+
+    fn generate_table_tbody(
+        &self,
+        rows: &mut Vec<RowView>,
+        profit_and_loss_list: &[ProfitAndLoss],
+        total: &ProfitAndLoss,
+    ) {
+        for profit_and_loss in profit_and_loss_list {
+            rows.push(self.generate_account_row(
+                "",
+                profit_and_loss.account_type(),
+                &profit_and_loss.get_all_fields(),
+            ));
+        }
+
+        let is_refund = total
+            .withholding_tax
+            .as_ref()
+            .is_some_and(|tax| tax.amount.is_sign_negative() && !tax.amount.is_zero());
+        let tr_class = if is_refund {
+            "group-total refund"
+        } else {
+            "group-total"
+        };
+        rows.push(self.generate_table_row_with_class(tr_class, &total.get_all_fields()));
+    }
+}
+
+fn amount(money: &Option<Money>) -> Decimal {
+    money.as_ref().map_or(Decimal::ZERO, |m| m.amount)
+}
+
+impl TableRenderer for ProfitAndLossManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
     }
 }
 
 impl TemplateManager for ProfitAndLossManager {
-    fn process_records(&self, records: Vec<StringRecord>) {
+    fn updates_annual_results(&self) -> bool {
+        true
+    }
+
+    fn column_specs(&self, broker: &dyn BrokerAdapter) -> &'static [ColumnSpec] {
+        broker.profit_and_loss_columns()
+    }
+
+    fn process_records(&self, records: Vec<StringRecord>, columns: &ColumnMap) {
+        let mut diagnostics = self.template_struct().diagnostics.borrow_mut();
         for record in records {
-            let profit_and_loss = ProfitAndLoss::from_record(record);
+            let profit_and_loss = ProfitAndLoss::from_record(&record, columns, &mut diagnostics);
+            let Some(trade_date) = profit_and_loss.trade_date else {
+                ParseDiagnostic::push_row_skipped(
+                    &mut diagnostics,
+                    &record,
+                    columns,
+                    "trade_date",
+                    "Row skipped: missing trade date",
+                );
+                continue;
+            };
+            self.insert(trade_date, profit_and_loss);
+        }
+    }
+
+    fn save(&self, store: &UserStore) -> Result<ImportSummary> {
+        store.save_profit_and_loss(self.profit_and_loss_map.borrow().values().flatten())
+    }
+
+    fn load(&self, store: &UserStore, query: &HistoryQuery) -> Result<()> {
+        for profit_and_loss in store.load_profit_and_loss(query)? {
             if let Some(trade_date) = profit_and_loss.trade_date {
-                self.profit_and_loss_map
-                    .borrow_mut()
-                    .entry(trade_date)
-                    .or_insert_with(Vec::new)
-                    .push(profit_and_loss);
+                self.insert(trade_date, profit_and_loss);
             }
         }
+        Ok(())
+    }
+
+    fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let headers = self.generate_table_header(ProfitAndLoss::new().get_all_fields());
+
+        let groups = self.group_profit_and_loss();
+        let totals = self.calculate_group_totals(&groups);
+        let mut rows = Vec::new();
+        for (key, profit_and_loss_list) in &groups {
+            self.generate_table_tbody(&mut rows, profit_and_loss_list, &totals[key]);
+        }
+        for (account_type, total) in self.calculate_account_type_totals() {
+            rows.push(self.generate_account_row(
+                "summary-total",
+                account_type,
+                &total.get_all_fields(),
+            ));
+        }
+        rows.push(self.generate_table_row_with_class(
+            "grand-total",
+            &self.calculate_grand_total().get_all_fields(),
+        ));
+
+        let table = TableView { headers, rows };
+        self.render_table(tera, "partials/profit_and_loss_table.html", &table)
+    }
+
+    fn generate_json(&self) -> Result<Value> {
+        let grouping = self.grouping();
+        let groups = self.group_profit_and_loss();
+        let totals = self.calculate_group_totals(&groups);
+        let groups = groups
+            .iter()
+            .map(|(key, profit_and_loss_list)| {
+                json!({
+                    "key": key,
+                    "label": grouping.label(key),
+                    "rows": profit_and_loss_list,
+                    "total": totals[key],
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(json!({
+            "grouping": grouping,
+            "groups": groups,
+            "account_type_totals": self
+                .calculate_account_type_totals()
+                .into_iter()
+                .map(|(account_type, total)| {
+                    json!({
+                        "account_type": account_type,
+                        "requires_self_filing": account_type.requires_self_filing(),
+                        "total": total,
+                    })
+                })
+                .collect::<Vec<_>>(),
+            "grand_total": self.calculate_grand_total(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn specific(trade_date: NaiveDate, realized_profit_and_loss: i64) -> ProfitAndLoss {
+        ProfitAndLoss {
+            trade_date: Some(trade_date),
+            account: Some("特定".to_string()),
+            realized_profit_and_loss: Some(Money::jpy(Decimal::from(realized_profit_and_loss))),
+            ..ProfitAndLoss::new()
+        }
     }
 
-    fn generate_html_table(&self) -> Result<String> {
-        let headers = ProfitAndLoss::new().get_all_fields();
-        let mut table = self.generate_table_header(headers);
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
 
-        table.push_str("<tbody>");
-        for (_, profit_and_loss_list) in self.profit_and_loss_map.borrow().iter() {
-            let _total = self.generate_table_tbody(&mut table, profit_and_loss_list);
+    fn manager(grouping: Grouping, rows: Vec<ProfitAndLoss>) -> ProfitAndLossManager {
+        let manager = ProfitAndLossManager::new();
+        manager.template_struct.grouping.set(Some(grouping));
+        for row in rows {
+            manager.insert(row.trade_date.unwrap(), row);
         }
-        table.push_str("</tbody></table>");
+        manager
+    }
+
+    #[test]
+    fn week_spanning_new_year_resets_year_to_date_per_trade_date() {
+        let manager = manager(
+            Grouping::Week,
+            vec![
+                specific(date(2024, 12, 2), 100_000),
+                specific(date(2024, 12, 31), 10_000),
+                specific(date(2025, 1, 2), -50_000),
+                specific(date(2025, 1, 6), 50_000),
+            ],
+        );
+        let totals = manager.calculate_group_totals(&manager.group_profit_and_loss());
+
+        // 2024/12/30〜2025/01/05の週: 2024年分は累計10万円に1万円を加えた分の税額、2025年分は損失で徴収なし
+        let new_year_week = &totals[&GroupKey::Date(date(2024, 12, 30))];
+        assert_eq!(
+            amount(&new_year_week.withholding_tax),
+            Decimal::from(22_346 - 20_315)
+        );
+        assert_eq!(
+            amount(&new_year_week.cumulative_realized_profit_and_loss),
+            Decimal::from(-50_000)
+        );
+
+        // 2025年の累計は2024年の損益を含まない
+        let next_week = &totals[&GroupKey::Date(date(2025, 1, 6))];
+        assert_eq!(
+            amount(&next_week.cumulative_realized_profit_and_loss),
+            Decimal::ZERO
+        );
+        assert_eq!(amount(&next_week.withholding_tax), Decimal::ZERO);
+    }
+
+    #[test]
+    fn withholding_floors_income_and_resident_tax_separately() {
+        let manager = manager(Grouping::Day, vec![specific(date(2025, 3, 3), 19)]);
+        let totals = manager.calculate_group_totals(&manager.group_profit_and_loss());
+
+        // 所得税 19 × 15.315% = 2.90985 → 2円、住民税 19 × 5% = 0.95 → 0円。
+        // 合計の税率で一度に切り捨てると 19 × 20.315% = 3.85985 → 3円になる
+        let total = &totals[&GroupKey::Date(date(2025, 3, 3))];
+        assert_eq!(amount(&total.income_tax), Decimal::from(2));
+        assert_eq!(amount(&total.resident_tax), Decimal::ZERO);
+        assert_eq!(amount(&total.withholding_tax), Decimal::from(2));
+    }
+
+    #[test]
+    fn daily_withholding_is_the_difference_in_year_to_date_tax() {
+        let manager = manager(
+            Grouping::Day,
+            vec![
+                specific(date(2025, 3, 3), 100_000),
+                specific(date(2025, 3, 4), -30_000),
+                specific(date(2025, 3, 5), 19),
+            ],
+        );
+        let totals = manager.calculate_group_totals(&manager.group_profit_and_loss());
+        let withholding = |day: u32| {
+            let total = &totals[&GroupKey::Date(date(2025, 3, day))];
+            (
+                amount(&total.income_tax),
+                amount(&total.resident_tax),
+                amount(&total.cumulative_withholding_tax),
+            )
+        };
 
-        Ok(table)
+        // 累計10万円: 所得税15,315円、住民税5,000円
+        assert_eq!(
+            withholding(3),
+            (
+                Decimal::from(15_315),
+                Decimal::from(5_000),
+                Decimal::from(20_315)
+            )
+        );
+        // 累計7万円: 所得税10,720円、住民税3,500円になるため、差額を還付する
+        assert_eq!(
+            withholding(4),
+            (
+                Decimal::from(-4_595),
+                Decimal::from(-1_500),
+                Decimal::from(14_220)
+            )
+        );
+        // 累計70,019円: 所得税10,723円、住民税3,500円
+        assert_eq!(
+            withholding(5),
+            (Decimal::from(3), Decimal::ZERO, Decimal::from(14_223))
+        );
+        assert_eq!(
+            amount(&manager.calculate_grand_total().withholding_tax),
+            Decimal::from(14_223)
+        );
     }
 }
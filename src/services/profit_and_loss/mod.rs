@@ -1,2 +1,3 @@
 pub mod lib;
+#[allow(clippy::module_inception)]
 pub mod profit_and_loss;
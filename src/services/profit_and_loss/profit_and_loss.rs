@@ -1,22 +1,34 @@
-use crate::{services::common, setting::TAX_RATE};
+use crate::services::{
+    account::{AccountTotals, AccountType},
+    common::{ParseDiagnostic, RecordReader},
+    csv::column_map::ColumnMap,
+    money::{Currency, Money},
+    tax::Withholding,
+};
 use chrono::NaiveDate;
 use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ProfitAndLoss {
-    pub trade_date: Option<NaiveDate>,               // 約定日
-    pub settlement_date: Option<NaiveDate>,          // 受渡日
-    pub security_code: Option<String>,               // 銘柄コード
-    pub security_name: Option<String>,               // 銘柄名
-    pub account: Option<String>,                     // 口座
-    pub shares: Option<i32>,                         // 数量[株]
-    pub asked_price: Option<f64>,                    // 売却/決済単価[円]
-    pub proceeds: Option<i32>,                       // 売却/決済額[円]
-    pub purchase_price: Option<f64>,                 // 平均取得価額[円]
-    pub realized_profit_and_loss: Option<i32>,       // 実現損益[円]
-    pub total_realized_profit_and_loss: Option<i32>, // 合計実現損益[円]
-    pub withholding_tax: Option<u32>,                // 源泉徴収税額
-    pub profit_and_loss: Option<i32>,                // 損益
+    pub trade_date: Option<NaiveDate>,                      // 約定日
+    pub settlement_date: Option<NaiveDate>,                 // 受渡日
+    pub security_code: Option<String>,                      // 銘柄コード
+    pub security_name: Option<String>,                      // 銘柄名
+    pub account: Option<String>,                            // 口座
+    pub shares: Option<i32>,                                // 数量[株]
+    pub asked_price: Option<Money>,                         // 売却/決済単価[円]
+    pub proceeds: Option<Money>,                            // 売却/決済額[円]
+    pub purchase_price: Option<Money>,                      // 平均取得価額[円]
+    pub realized_profit_and_loss: Option<Money>,            // 実現損益[円]
+    pub total_realized_profit_and_loss: Option<Money>,      // 合計実現損益[円]
+    pub cumulative_realized_profit_and_loss: Option<Money>, // 年間累計損益(特定口座)
+    pub cumulative_withholding_tax: Option<Money>,          // 年間累計源泉徴収税額
+    pub income_tax: Option<Money>,                          // 所得税
+    pub resident_tax: Option<Money>,                        // 住民税
+    pub withholding_tax: Option<Money>,                     // 源泉徴収税額
+    pub profit_and_loss: Option<Money>,                     // 損益
 }
 
 impl ProfitAndLoss {
@@ -33,24 +45,38 @@ impl ProfitAndLoss {
             purchase_price: None,
             realized_profit_and_loss: None,
             total_realized_profit_and_loss: None,
+            cumulative_realized_profit_and_loss: None,
+            cumulative_withholding_tax: None,
+            income_tax: None,
+            resident_tax: None,
             withholding_tax: None,
             profit_and_loss: None,
         }
     }
 
-    pub fn from_record(record: StringRecord) -> Self {
+    pub fn from_record(
+        record: &StringRecord,
+        columns: &ColumnMap,
+        diagnostics: &mut Vec<ParseDiagnostic>,
+    ) -> Self {
+        let mut reader = RecordReader::new(record, columns, diagnostics);
+        let jpy = Currency::jpy();
         ProfitAndLoss {
-            trade_date: common::parse_date(record.get(0)),
-            settlement_date: common::parse_date(record.get(1)),
-            security_code: common::parse_string(record.get(2)),
-            security_name: common::parse_string(record.get(3)),
-            account: common::parse_string(record.get(4)),
-            shares: common::parse_int(record.get(7)),
-            asked_price: common::parse_float(record.get(8)),
-            proceeds: common::parse_int(record.get(9)),
-            purchase_price: common::parse_float(record.get(10)),
-            realized_profit_and_loss: common::parse_int(record.get(11)),
+            trade_date: reader.date("trade_date"),
+            settlement_date: reader.date("settlement_date"),
+            security_code: reader.string("security_code"),
+            security_name: reader.string("security_name"),
+            account: reader.string("account"),
+            shares: reader.int("shares"),
+            asked_price: reader.money("asked_price", &jpy),
+            proceeds: reader.money("proceeds", &jpy),
+            purchase_price: reader.money("purchase_price", &jpy),
+            realized_profit_and_loss: reader.money("realized_profit_and_loss", &jpy),
             total_realized_profit_and_loss: None,
+            cumulative_realized_profit_and_loss: None,
+            cumulative_withholding_tax: None,
+            income_tax: None,
+            resident_tax: None,
             withholding_tax: None,
             profit_and_loss: None,
         }
@@ -72,56 +98,114 @@ impl ProfitAndLoss {
             ("shares".to_string(), self.shares.map(|s| s.to_string())),
             (
                 "asked_price".to_string(),
-                self.asked_price.map(|p| p.to_string()),
+                self.asked_price.as_ref().map(|p| p.to_string()),
+            ),
+            (
+                "proceeds".to_string(),
+                self.proceeds.as_ref().map(|p| p.to_string()),
             ),
-            ("proceeds".to_string(), self.proceeds.map(|p| p.to_string())),
             (
                 "purchase_price".to_string(),
-                self.purchase_price.map(|p| p.to_string()),
+                self.purchase_price.as_ref().map(|p| p.to_string()),
             ),
             (
                 "realized_profit_and_loss".to_string(),
-                self.realized_profit_and_loss.map(|p| p.to_string()),
+                self.realized_profit_and_loss
+                    .as_ref()
+                    .map(|p| p.to_string()),
             ),
             (
                 "total_realized_profit_and_loss".to_string(),
-                self.total_realized_profit_and_loss.map(|p| p.to_string()),
+                self.total_realized_profit_and_loss
+                    .as_ref()
+                    .map(|p| p.to_string()),
+            ),
+            (
+                "cumulative_realized_profit_and_loss".to_string(),
+                self.cumulative_realized_profit_and_loss
+                    .as_ref()
+                    .map(|p| p.to_string()),
+            ),
+            (
+                "cumulative_withholding_tax".to_string(),
+                self.cumulative_withholding_tax
+                    .as_ref()
+                    .map(|p| p.to_string()),
+            ),
+            (
+                "income_tax".to_string(),
+                self.income_tax.as_ref().map(|p| p.to_string()),
+            ),
+            (
+                "resident_tax".to_string(),
+                self.resident_tax.as_ref().map(|p| p.to_string()),
             ),
             (
                 "withholding_tax".to_string(),
-                self.withholding_tax.map(|p| p.to_string()),
+                self.withholding_tax.as_ref().map(|p| p.to_string()),
             ),
             (
                 "profit_and_loss".to_string(),
-                self.profit_and_loss.map(|p| p.to_string()),
+                self.profit_and_loss.as_ref().map(|p| p.to_string()),
             ),
         ]
     }
 
+    pub fn account_type(&self) -> AccountType {
+        AccountType::parse(self.account.as_deref())
+    }
+
+    // 特定口座の源泉徴収は年初からの累計損益に対して計算され、
+    // その差分がこのグループで徴収（負なら還付）される額になる。一般口座の損益は申告で精算する
     pub fn new_total_realized_profit_and_loss(
-        (specific_account_total, nisa_account_total): (i32, i32),
+        totals: AccountTotals,
+        year_to_date: Decimal, // 同じ年のこれまでの特定口座の累計損益
     ) -> Self {
-        let withholding_tax = if specific_account_total < 0 {
-            0
-        } else {
-            (specific_account_total as f64 * TAX_RATE) as u32
-        };
-        let total = specific_account_total + nisa_account_total;
+        Self::new_year_to_date_total(&[(totals, year_to_date)])
+    }
+
+    // 年をまたぐグループ（年末年始の週など）の合計。年ごとの損益とその年のこれまでの累計損益の組を
+    // 年の順に受け取り、年ごとの税額の差分を足し合わせる。累計欄には最後の年の累計を出す
+    pub fn new_year_to_date_total(annual_totals: &[(AccountTotals, Decimal)]) -> Self {
+        let mut totals = AccountTotals::default();
+        let mut withholding = Withholding::default();
+        let mut cumulative = Decimal::ZERO;
+        for (annual_total, year_to_date) in annual_totals {
+            totals.specific += annual_total.specific;
+            totals.general += annual_total.general;
+            totals.tax_exempt += annual_total.tax_exempt;
+            cumulative = year_to_date + annual_total.specific;
+            withholding = withholding + Withholding::calculate(cumulative)
+                - Withholding::calculate(*year_to_date);
+        }
+        let cumulative_withholding = Withholding::calculate(cumulative);
 
         ProfitAndLoss {
-            trade_date: None,
-            settlement_date: None,
-            security_code: None,
-            security_name: None,
-            account: None,
-            shares: None,
-            asked_price: None,
-            proceeds: None,
-            purchase_price: None,
-            realized_profit_and_loss: None,
-            total_realized_profit_and_loss: Some(total),
-            withholding_tax: Some(withholding_tax),
-            profit_and_loss: Some(total - withholding_tax as i32),
+            cumulative_realized_profit_and_loss: Some(Money::jpy(cumulative)),
+            cumulative_withholding_tax: Some(Money::jpy(cumulative_withholding.total())),
+            ..Self::new_total(totals.total(), Some(withholding))
+        }
+    }
+
+    // 銘柄・口座ごとの小計。年をまたいで集計するため源泉徴収税額は計算しない
+    pub fn new_group_total(totals: AccountTotals) -> Self {
+        Self::new_total(totals.total(), None)
+    }
+
+    // 全体の合計。源泉徴収税額は年ごとの特定口座の損益に対する税額の合計
+    pub fn new_grand_total(totals: AccountTotals, withholding: Withholding) -> Self {
+        Self::new_total(totals.total(), Some(withholding))
+    }
+
+    fn new_total(total: Decimal, withholding: Option<Withholding>) -> Self {
+        let withholding_tax = withholding.map_or(Decimal::ZERO, |w| w.total());
+        ProfitAndLoss {
+            total_realized_profit_and_loss: Some(Money::jpy(total)),
+            income_tax: withholding.map(|w| Money::jpy(w.income_tax)),
+            resident_tax: withholding.map(|w| Money::jpy(w.resident_tax)),
+            withholding_tax: withholding.map(|w| Money::jpy(w.total())),
+            profit_and_loss: Some(Money::jpy(total - withholding_tax)),
+            ..Self::new()
         }
     }
 }
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use rusqlite::params;
+use std::collections::BTreeMap;
+
+use super::lib::{decode_decimal, encode_decimal, UserStore};
+use crate::services::loss_carry_forward::loss_carry_forward::AnnualResult;
+
+impl UserStore<'_> {
+    // 集計済みの年間損益。申告の有無だけを記録していて、まだ集計していない年は含まない
+    pub fn load_annual_results(&self) -> Result<Vec<AnnualResult>> {
+        let connection = self.store.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT year, transfer_income, dividend_income, filed
+             FROM annual_results
+             WHERE user_id = ?1
+               AND transfer_income IS NOT NULL
+               AND dividend_income IS NOT NULL
+             ORDER BY year",
+        )?;
+        let rows = statement.query_map(params![self.user_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            let (year, transfer_income, dividend_income, filed) = row?;
+            results.push(AnnualResult {
+                year,
+                transfer_income: decode_decimal(Some(transfer_income))?.unwrap_or_default(),
+                dividend_income: decode_decimal(Some(dividend_income))?.unwrap_or_default(),
+                filed,
+            });
+        }
+
+        Ok(results)
+    }
+
+    // 年ごとの申告の有無
+    pub fn load_filings(&self) -> Result<BTreeMap<i32, bool>> {
+        let connection = self.store.connection()?;
+        let mut statement =
+            connection.prepare("SELECT year, filed FROM annual_results WHERE user_id = ?1")?;
+        let rows =
+            statement.query_map(params![self.user_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // 明細から集計し直した年間損益を保存する。記録済みの申告の有無はそのまま残す
+    pub fn save_annual_results(&self, results: &[AnnualResult]) -> Result<()> {
+        let mut connection = self.store.connection()?;
+        let transaction = connection.transaction()?;
+        {
+            let mut statement = transaction.prepare(
+                "INSERT INTO annual_results (user_id, year, transfer_income, dividend_income, filed)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (user_id, year) DO UPDATE SET
+                     transfer_income = excluded.transfer_income,
+                     dividend_income = excluded.dividend_income",
+            )?;
+            for result in results {
+                statement.execute(params![
+                    self.user_id,
+                    result.year,
+                    encode_decimal(result.transfer_income),
+                    encode_decimal(result.dividend_income),
+                    result.filed,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    // 明細のない年でも申告の有無を記録できるよう、行がなければ損益は空のまま作る
+    pub fn set_filed(&self, year: i32, filed: bool) -> Result<()> {
+        let connection = self.store.connection()?;
+        connection.execute(
+            "INSERT INTO annual_results (user_id, year, filed)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT (user_id, year) DO UPDATE SET filed = excluded.filed",
+            params![self.user_id, year, filed],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lib::Store;
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn result(year: i32, transfer_income: i64) -> AnnualResult {
+        AnnualResult {
+            year,
+            transfer_income: Decimal::from(transfer_income),
+            dividend_income: Decimal::ZERO,
+            filed: true,
+        }
+    }
+
+    #[test]
+    fn saving_results_keeps_the_recorded_filing() {
+        let store = Store::open(":memory:").unwrap();
+        let store = store.for_user(1);
+
+        // 申告の有無だけを記録した年は、集計するまで年間損益に含めない
+        store.set_filed(2024, false).unwrap();
+        assert!(store.load_annual_results().unwrap().is_empty());
+
+        store
+            .save_annual_results(&[result(2024, -100_000), result(2025, 30_000)])
+            .unwrap();
+        let results = store.load_annual_results().unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|r| (r.year, r.transfer_income, r.filed))
+                .collect::<Vec<_>>(),
+            vec![
+                (2024, Decimal::from(-100_000), false),
+                (2025, Decimal::from(30_000), true)
+            ]
+        );
+    }
+}
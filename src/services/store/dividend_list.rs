@@ -0,0 +1,128 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use super::lib::{
+    decode_decimal, encode_decimal, HistoryQuery, ImportSummary, Occurrences, UserStore,
+};
+use crate::services::{
+    dividend_list::dividend_list::DividendList,
+    money::{Currency, Money},
+};
+
+impl UserStore<'_> {
+    // 入金日・銘柄コード・口座・数量・税引前配当額が以前の取込の明細と一致する明細は重複として読み飛ばす
+    pub fn save_dividend_list<'a>(
+        &self,
+        dividend_list: impl IntoIterator<Item = &'a DividendList>,
+    ) -> Result<ImportSummary> {
+        let mut connection = self.store.connection()?;
+        let transaction = connection.transaction()?;
+        let mut summary = ImportSummary::default();
+        {
+            let mut statement = transaction.prepare(
+                "INSERT OR IGNORE INTO dividend_list (
+                    user_id, settlement_date, product, account, security_code, security_name,
+                    currency, unit_price, shares, dividends_before_tax, taxes, net_amount_received,
+                    foreign_taxes, domestic_taxes, payment_date, occurrence
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            )?;
+            let amount = |money: &Option<Money>| money.as_ref().map(|m| encode_decimal(m.amount));
+            let mut occurrences = Occurrences::new();
+            for dividend in dividend_list {
+                let occurrence = occurrences.next((
+                    dividend.settlement_date,
+                    dividend.security_code.clone().unwrap_or_default(),
+                    dividend.account.clone().unwrap_or_default(),
+                    dividend.shares.unwrap_or_default(),
+                    amount(&dividend.dividends_before_tax).unwrap_or_default(),
+                ));
+                let inserted = statement.execute(params![
+                    self.user_id,
+                    dividend.settlement_date,
+                    dividend.product,
+                    dividend.account,
+                    dividend.security_code,
+                    dividend.security_name,
+                    dividend.currency,
+                    amount(&dividend.unit_price),
+                    dividend.shares,
+                    amount(&dividend.dividends_before_tax),
+                    amount(&dividend.taxes),
+                    amount(&dividend.net_amount_received),
+                    amount(&dividend.foreign_taxes),
+                    amount(&dividend.domestic_taxes),
+                    dividend.payment_date,
+                    occurrence,
+                ])?;
+                if inserted > 0 {
+                    summary.inserted += 1;
+                } else {
+                    summary.duplicates += 1;
+                }
+            }
+        }
+        transaction.commit()?;
+
+        Ok(summary)
+    }
+
+    pub fn load_dividend_list(&self, query: &HistoryQuery) -> Result<Vec<DividendList>> {
+        let connection = self.store.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT settlement_date, product, account, security_code, security_name, currency,
+                    unit_price, shares, dividends_before_tax, taxes, net_amount_received,
+                    foreign_taxes, domestic_taxes, payment_date
+             FROM dividend_list
+             WHERE user_id = ?1
+               AND (?2 IS NULL OR settlement_date >= ?2)
+               AND (?3 IS NULL OR settlement_date <= ?3)
+               AND (?4 IS NULL OR security_code = ?4)
+             ORDER BY settlement_date, id",
+        )?;
+        let rows = statement.query_map(
+            params![self.user_id, query.from, query.to, query.security_code],
+            |row| {
+                Ok((
+                    (row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?),
+                    (row.get(4)?, row.get::<_, Option<String>>(5)?, row.get(6)?),
+                    (row.get(7)?, row.get(8)?, row.get(9)?, row.get(10)?),
+                    (row.get(11)?, row.get(12)?, row.get(13)?),
+                ))
+            },
+        )?;
+
+        let mut dividend_list = Vec::new();
+        for row in rows {
+            let (
+                (settlement_date, product, account, security_code),
+                (security_name, currency, unit_price),
+                (shares, dividends_before_tax, taxes, net_amount_received),
+                (foreign_taxes, domestic_taxes, payment_date),
+            ) = row?;
+            let code = Currency::from_label(currency.as_deref().unwrap_or(""));
+            let money = |amount: Option<String>| -> Result<Option<Money>> {
+                Ok(decode_decimal(amount)?.map(|amount| Money::new(amount, code.clone())))
+            };
+
+            let mut dividend = DividendList::new();
+            dividend.settlement_date = Some(settlement_date);
+            dividend.payment_date = payment_date;
+            dividend.product = product;
+            dividend.account = account;
+            dividend.security_code = security_code;
+            dividend.security_name = security_name;
+            dividend.unit_price = money(unit_price)?;
+            dividend.shares = shares;
+            dividend.dividends_before_tax = money(dividends_before_tax)?;
+            dividend.taxes = money(taxes)?;
+            dividend.foreign_taxes = money(foreign_taxes)?;
+            dividend.domestic_taxes = money(domestic_taxes)?;
+            dividend.net_amount_received = money(net_amount_received)?;
+            dividend.currency = currency;
+            // 内訳を保存していない取り込み済みの明細は、読み込み時に内訳を推定する
+            dividend_list.push(dividend.with_tax_breakdown());
+        }
+
+        Ok(dividend_list)
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use super::lib::{decode_decimal, encode_decimal, HistoryQuery, ImportSummary, UserStore};
+use crate::services::{fx_rate::fx_rate::FxRate, money::Currency};
+
+impl UserStore<'_> {
+    // 同じ日付・通貨のレートが保存済みなら重複として読み飛ばす
+    pub fn save_fx_rates<'a>(
+        &self,
+        fx_rates: impl IntoIterator<Item = &'a FxRate>,
+    ) -> Result<ImportSummary> {
+        let mut connection = self.store.connection()?;
+        let transaction = connection.transaction()?;
+        let mut summary = ImportSummary::default();
+        {
+            let mut statement = transaction.prepare(
+                "INSERT OR IGNORE INTO fx_rates (user_id, rate_date, currency, ttm)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for fx_rate in fx_rates {
+                let (Some(rate_date), Some(currency), Some(ttm)) =
+                    (fx_rate.rate_date, &fx_rate.currency, fx_rate.ttm)
+                else {
+                    continue;
+                };
+                let inserted = statement.execute(params![
+                    self.user_id,
+                    rate_date,
+                    currency.to_string(),
+                    encode_decimal(ttm),
+                ])?;
+                if inserted > 0 {
+                    summary.inserted += 1;
+                } else {
+                    summary.duplicates += 1;
+                }
+            }
+        }
+        transaction.commit()?;
+
+        Ok(summary)
+    }
+
+    pub fn load_fx_rates(&self, query: &HistoryQuery) -> Result<Vec<FxRate>> {
+        let connection = self.store.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT rate_date, currency, ttm
+             FROM fx_rates
+             WHERE user_id = ?1
+               AND (?2 IS NULL OR rate_date >= ?2)
+               AND (?3 IS NULL OR rate_date <= ?3)
+             ORDER BY rate_date, currency",
+        )?;
+        let rows = statement.query_map(params![self.user_id, query.from, query.to], |row| {
+            Ok((row.get(0)?, row.get::<_, String>(1)?, row.get(2)?))
+        })?;
+
+        let mut fx_rates = Vec::new();
+        for row in rows {
+            let (rate_date, currency, ttm) = row?;
+            let mut fx_rate = FxRate::new();
+            fx_rate.rate_date = Some(rate_date);
+            fx_rate.currency = Some(Currency::from_label(&currency));
+            fx_rate.ttm = decode_decimal(Some(ttm))?;
+            fx_rates.push(fx_rate);
+        }
+
+        Ok(fx_rates)
+    }
+}
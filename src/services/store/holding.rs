@@ -0,0 +1,94 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use super::lib::{decode_decimal, encode_decimal, HistoryQuery, ImportSummary, UserStore};
+use crate::services::{holdings::holding::Holding, money::Money};
+
+impl UserStore<'_> {
+    // 保有証券は取込時点の一覧なので、同じ証券会社の以前の一覧を削除してから保存する。
+    // 証券会社を記録する前に取り込んだ一覧は、どの証券会社のものか分からないため置き換える
+    pub fn replace_holdings<'a>(
+        &self,
+        broker: &str,
+        holdings: impl IntoIterator<Item = &'a Holding>,
+    ) -> Result<ImportSummary> {
+        let mut connection = self.store.connection()?;
+        let transaction = connection.transaction()?;
+        let mut summary = ImportSummary::default();
+        transaction.execute(
+            "DELETE FROM holdings WHERE user_id = ?1 AND (broker = ?2 OR broker IS NULL)",
+            params![self.user_id, broker],
+        )?;
+        {
+            let mut statement = transaction.prepare(
+                "INSERT INTO holdings (
+                    user_id, security_code, security_name, account, shares,
+                    acquisition_price, current_price, valuation, unrealized_profit_and_loss,
+                    broker
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?;
+            let amount = |money: &Option<Money>| money.as_ref().map(|m| encode_decimal(m.amount));
+            for holding in holdings {
+                statement.execute(params![
+                    self.user_id,
+                    holding.security_code,
+                    holding.security_name,
+                    holding.account,
+                    holding.shares,
+                    amount(&holding.acquisition_price),
+                    amount(&holding.current_price),
+                    amount(&holding.valuation),
+                    amount(&holding.unrealized_profit_and_loss),
+                    broker,
+                ])?;
+                summary.inserted += 1;
+            }
+        }
+        transaction.commit()?;
+
+        Ok(summary)
+    }
+
+    // 保有証券は日付を持たないため、絞り込みは銘柄コードのみ
+    pub fn load_holdings(&self, query: &HistoryQuery) -> Result<Vec<Holding>> {
+        let connection = self.store.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT security_code, security_name, account, shares,
+                    acquisition_price, current_price, valuation, unrealized_profit_and_loss
+             FROM holdings
+             WHERE user_id = ?1
+               AND (?2 IS NULL OR security_code = ?2)
+             ORDER BY id",
+        )?;
+        let rows = statement.query_map(params![self.user_id, query.security_code], |row| {
+            Ok((
+                (row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?),
+                (row.get(4)?, row.get(5)?, row.get(6)?, row.get(7)?),
+            ))
+        })?;
+
+        let mut holdings = Vec::new();
+        for row in rows {
+            let (
+                (security_code, security_name, account, shares),
+                (acquisition_price, current_price, valuation, unrealized_profit_and_loss),
+            ) = row?;
+            let money = |amount: Option<String>| -> Result<Option<Money>> {
+                Ok(decode_decimal(amount)?.map(Money::jpy))
+            };
+
+            let mut holding = Holding::new();
+            holding.security_code = security_code;
+            holding.security_name = security_name;
+            holding.account = account;
+            holding.shares = shares;
+            holding.acquisition_price = money(acquisition_price)?;
+            holding.current_price = money(current_price)?;
+            holding.valuation = money(valuation)?;
+            holding.unrealized_profit_and_loss = money(unrealized_profit_and_loss)?;
+            holdings.push(holding.with_valuation());
+        }
+
+        Ok(holdings)
+    }
+}
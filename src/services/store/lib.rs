@@ -0,0 +1,323 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    path::Path,
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::services::grouping::Grouping;
+
+// スキーマの変更履歴。PRAGMA user_versionに適用済みの数を記録し、未適用のものだけを順に流す。
+// 重複判定の自然キーにNULLが含まれても一致とみなせるよう、インデックスはifnullで正規化する
+const MIGRATIONS: [&str; 11] = [
+    "
+CREATE TABLE IF NOT EXISTS dividend_list (
+    id INTEGER PRIMARY KEY,
+    settlement_date TEXT NOT NULL,
+    product TEXT,
+    account TEXT,
+    security_code TEXT,
+    security_name TEXT,
+    currency TEXT,
+    unit_price TEXT,
+    shares INTEGER,
+    dividends_before_tax TEXT,
+    taxes TEXT,
+    net_amount_received TEXT,
+    imported_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE UNIQUE INDEX IF NOT EXISTS dividend_list_natural_key ON dividend_list (
+    settlement_date,
+    ifnull(security_code, ''),
+    ifnull(account, ''),
+    ifnull(shares, 0),
+    ifnull(dividends_before_tax, '')
+);
+CREATE TABLE IF NOT EXISTS profit_and_loss (
+    id INTEGER PRIMARY KEY,
+    trade_date TEXT NOT NULL,
+    settlement_date TEXT,
+    security_code TEXT,
+    security_name TEXT,
+    account TEXT,
+    shares INTEGER,
+    asked_price TEXT,
+    proceeds TEXT,
+    purchase_price TEXT,
+    realized_profit_and_loss TEXT,
+    imported_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE UNIQUE INDEX IF NOT EXISTS profit_and_loss_natural_key ON profit_and_loss (
+    trade_date,
+    ifnull(security_code, ''),
+    ifnull(account, ''),
+    ifnull(shares, 0),
+    ifnull(realized_profit_and_loss, '')
+);
+",
+    // ユーザーごとに明細を分ける。ユーザー導入前に取り込んだ明細はuser_id = 0（所有者なし）になる
+    "
+CREATE TABLE users (
+    id INTEGER PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+ALTER TABLE dividend_list ADD COLUMN user_id INTEGER NOT NULL DEFAULT 0;
+DROP INDEX dividend_list_natural_key;
+CREATE UNIQUE INDEX dividend_list_natural_key ON dividend_list (
+    user_id,
+    settlement_date,
+    ifnull(security_code, ''),
+    ifnull(account, ''),
+    ifnull(shares, 0),
+    ifnull(dividends_before_tax, '')
+);
+ALTER TABLE profit_and_loss ADD COLUMN user_id INTEGER NOT NULL DEFAULT 0;
+DROP INDEX profit_and_loss_natural_key;
+CREATE UNIQUE INDEX profit_and_loss_natural_key ON profit_and_loss (
+    user_id,
+    trade_date,
+    ifnull(security_code, ''),
+    ifnull(account, ''),
+    ifnull(shares, 0),
+    ifnull(realized_profit_and_loss, '')
+);
+",
+    // 損失の繰越控除のため、年ごとの損益と確定申告の有無を保存する
+    "
+CREATE TABLE annual_results (
+    user_id INTEGER NOT NULL,
+    year INTEGER NOT NULL,
+    transfer_income TEXT NOT NULL,
+    dividend_income TEXT NOT NULL,
+    filed INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (user_id, year)
+);
+",
+    // NISAの投資枠の計算のため、約定履歴を保存する
+    "
+CREATE TABLE trade_history (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    trade_date TEXT NOT NULL,
+    settlement_date TEXT,
+    security_code TEXT,
+    security_name TEXT,
+    account TEXT,
+    trade_type TEXT,
+    shares INTEGER,
+    unit_price TEXT,
+    fees TEXT,
+    settlement_amount TEXT,
+    imported_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE UNIQUE INDEX trade_history_natural_key ON trade_history (
+    user_id,
+    trade_date,
+    ifnull(security_code, ''),
+    ifnull(security_name, ''),
+    ifnull(account, ''),
+    ifnull(trade_type, ''),
+    ifnull(shares, 0),
+    ifnull(settlement_amount, '')
+);
+",
+    // 保有証券は取り込むたびに最新の一覧で置き換えるため、重複判定のキーは持たない
+    "
+CREATE TABLE holdings (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    security_code TEXT,
+    security_name TEXT,
+    account TEXT,
+    shares INTEGER,
+    acquisition_price TEXT,
+    current_price TEXT,
+    valuation TEXT,
+    unrealized_profit_and_loss TEXT,
+    imported_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX holdings_user_id ON holdings (user_id);
+",
+    // 外貨建ての配当を円に換算するため、通貨ごと・日付ごとの為替レート（TTM）を保存する
+    "
+CREATE TABLE fx_rates (
+    user_id INTEGER NOT NULL,
+    rate_date TEXT NOT NULL,
+    currency TEXT NOT NULL,
+    ttm TEXT NOT NULL,
+    imported_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, rate_date, currency)
+);
+",
+    // 外国税額控除のため、税額のうち外国源泉税と国内源泉税の内訳を保存する
+    "
+ALTER TABLE dividend_list ADD COLUMN foreign_taxes TEXT;
+ALTER TABLE dividend_list ADD COLUMN domestic_taxes TEXT;
+",
+    // 外国源泉税を支払確定日の為替レートで円換算するため、配当の支払確定日を保存する
+    "
+ALTER TABLE dividend_list ADD COLUMN payment_date TEXT;
+",
+    // 複数の証券会社の保有証券を残すため、保有証券に取り込んだ証券会社を記録する
+    "
+ALTER TABLE holdings ADD COLUMN broker TEXT;
+",
+    // 同じファイルにある同じ内容の明細（同じ日の同じ数量の約定など）をすべて保存するため、
+    // ファイル内で同じ内容の行の何件目かを自然キーに加える。以前の取込との重複だけを読み飛ばす
+    "
+ALTER TABLE dividend_list ADD COLUMN occurrence INTEGER NOT NULL DEFAULT 0;
+DROP INDEX dividend_list_natural_key;
+CREATE UNIQUE INDEX dividend_list_natural_key ON dividend_list (
+    user_id,
+    settlement_date,
+    ifnull(security_code, ''),
+    ifnull(account, ''),
+    ifnull(shares, 0),
+    ifnull(dividends_before_tax, ''),
+    occurrence
+);
+ALTER TABLE profit_and_loss ADD COLUMN occurrence INTEGER NOT NULL DEFAULT 0;
+DROP INDEX profit_and_loss_natural_key;
+CREATE UNIQUE INDEX profit_and_loss_natural_key ON profit_and_loss (
+    user_id,
+    trade_date,
+    ifnull(security_code, ''),
+    ifnull(account, ''),
+    ifnull(shares, 0),
+    ifnull(realized_profit_and_loss, ''),
+    occurrence
+);
+ALTER TABLE trade_history ADD COLUMN occurrence INTEGER NOT NULL DEFAULT 0;
+DROP INDEX trade_history_natural_key;
+CREATE UNIQUE INDEX trade_history_natural_key ON trade_history (
+    user_id,
+    trade_date,
+    ifnull(security_code, ''),
+    ifnull(security_name, ''),
+    ifnull(account, ''),
+    ifnull(trade_type, ''),
+    ifnull(shares, 0),
+    ifnull(settlement_amount, ''),
+    occurrence
+);
+",
+    // 年間損益は明細の取込のたびに集計して保存する。まだ集計していない年は損益を空にできるようにし、
+    // 申告の有無を記録するために入れていた仮の損益は捨てる
+    "
+CREATE TABLE annual_results_new (
+    user_id INTEGER NOT NULL,
+    year INTEGER NOT NULL,
+    transfer_income TEXT,
+    dividend_income TEXT,
+    filed INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (user_id, year)
+);
+INSERT INTO annual_results_new (user_id, year, filed)
+SELECT user_id, year, filed FROM annual_results;
+DROP TABLE annual_results;
+ALTER TABLE annual_results_new RENAME TO annual_results;
+",
+];
+
+// 保存時に新規追加した件数と、以前の取込で保存済みだったため読み飛ばした件数
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct ImportSummary {
+    pub inserted: usize,
+    pub duplicates: usize,
+}
+
+// 保存済みの明細を取り出すときの絞り込み条件
+#[derive(Debug, Default, Deserialize)]
+pub struct HistoryQuery {
+    pub from: Option<NaiveDate>,       // この日以降（当日を含む）
+    pub to: Option<NaiveDate>,         // この日以前（当日を含む）
+    pub security_code: Option<String>, // 銘柄コード
+    pub group: Option<Grouping>,       // 小計を出す単位
+}
+
+pub struct Store {
+    connection: Mutex<Connection>,
+}
+
+impl Store {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut connection = Connection::open(path)?;
+        Self::migrate(&mut connection)?;
+        Ok(Store {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn migrate(connection: &mut Connection) -> Result<()> {
+        let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = connection.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", index + 1)?;
+            transaction.commit()?;
+        }
+        Ok(())
+    }
+
+    // 明細の読み書きをログイン中のユーザーの分に限定する
+    pub fn for_user(&self, user_id: i64) -> UserStore<'_> {
+        UserStore {
+            store: self,
+            user_id,
+        }
+    }
+
+    pub(super) fn connection(&self) -> Result<MutexGuard<'_, Connection>> {
+        self.connection
+            .lock()
+            .map_err(|_| anyhow!("Store connection is poisoned"))
+    }
+}
+
+pub struct UserStore<'a> {
+    pub(super) store: &'a Store,
+    pub(super) user_id: i64,
+}
+
+// 金額は桁落ちしないよう正規化した10進文字列で保存する。
+// 正規化しておくことで「2000」と「2000.00」も同じ自然キーになる
+pub(super) fn encode_decimal(amount: Decimal) -> String {
+    amount.normalize().to_string()
+}
+
+// ファイル内で同じ自然キーの行が何件目か（0始まり）。同じファイルの同じ内容の行は別の明細として保存し、
+// 以前の取込で保存済みの同じ件数分だけを重複として読み飛ばす
+pub(super) struct Occurrences<K> {
+    counts: HashMap<K, i64>,
+}
+
+impl<K: Hash + Eq> Occurrences<K> {
+    pub(super) fn new() -> Self {
+        Occurrences {
+            counts: HashMap::new(),
+        }
+    }
+
+    pub(super) fn next(&mut self, key: K) -> i64 {
+        let count = self.counts.entry(key).or_default();
+        *count += 1;
+        *count - 1
+    }
+}
+
+pub(super) fn decode_decimal(value: Option<String>) -> Result<Option<Decimal>> {
+    value
+        .map(|value| {
+            Decimal::from_str(&value)
+                .map_err(|e| anyhow!("Failed to parse stored decimal '{}': {}", value, e))
+        })
+        .transpose()
+}
@@ -0,0 +1,8 @@
+pub mod annual_result;
+pub mod dividend_list;
+pub mod fx_rate;
+pub mod holding;
+pub mod lib;
+pub mod profit_and_loss;
+pub mod trade_history;
+pub mod user;
@@ -0,0 +1,167 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use super::lib::{
+    decode_decimal, encode_decimal, HistoryQuery, ImportSummary, Occurrences, UserStore,
+};
+use crate::services::{money::Money, profit_and_loss::profit_and_loss::ProfitAndLoss};
+
+impl UserStore<'_> {
+    // 約定日・銘柄コード・口座・数量・実現損益が以前の取込の明細と一致する明細は重複として読み飛ばす
+    pub fn save_profit_and_loss<'a>(
+        &self,
+        profit_and_loss_list: impl IntoIterator<Item = &'a ProfitAndLoss>,
+    ) -> Result<ImportSummary> {
+        let mut connection = self.store.connection()?;
+        let transaction = connection.transaction()?;
+        let mut summary = ImportSummary::default();
+        {
+            let mut statement = transaction.prepare(
+                "INSERT OR IGNORE INTO profit_and_loss (
+                    user_id, trade_date, settlement_date, security_code, security_name, account,
+                    shares, asked_price, proceeds, purchase_price, realized_profit_and_loss,
+                    occurrence
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )?;
+            let amount = |money: &Option<Money>| money.as_ref().map(|m| encode_decimal(m.amount));
+            let mut occurrences = Occurrences::new();
+            for profit_and_loss in profit_and_loss_list {
+                let occurrence = occurrences.next((
+                    profit_and_loss.trade_date,
+                    profit_and_loss.security_code.clone().unwrap_or_default(),
+                    profit_and_loss.account.clone().unwrap_or_default(),
+                    profit_and_loss.shares.unwrap_or_default(),
+                    amount(&profit_and_loss.realized_profit_and_loss).unwrap_or_default(),
+                ));
+                let inserted = statement.execute(params![
+                    self.user_id,
+                    profit_and_loss.trade_date,
+                    profit_and_loss.settlement_date,
+                    profit_and_loss.security_code,
+                    profit_and_loss.security_name,
+                    profit_and_loss.account,
+                    profit_and_loss.shares,
+                    amount(&profit_and_loss.asked_price),
+                    amount(&profit_and_loss.proceeds),
+                    amount(&profit_and_loss.purchase_price),
+                    amount(&profit_and_loss.realized_profit_and_loss),
+                    occurrence,
+                ])?;
+                if inserted > 0 {
+                    summary.inserted += 1;
+                } else {
+                    summary.duplicates += 1;
+                }
+            }
+        }
+        transaction.commit()?;
+
+        Ok(summary)
+    }
+
+    pub fn load_profit_and_loss(&self, query: &HistoryQuery) -> Result<Vec<ProfitAndLoss>> {
+        let connection = self.store.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT trade_date, settlement_date, security_code, security_name, account, shares,
+                    asked_price, proceeds, purchase_price, realized_profit_and_loss
+             FROM profit_and_loss
+             WHERE user_id = ?1
+               AND (?2 IS NULL OR trade_date >= ?2)
+               AND (?3 IS NULL OR trade_date <= ?3)
+               AND (?4 IS NULL OR security_code = ?4)
+             ORDER BY trade_date, id",
+        )?;
+        let rows = statement.query_map(
+            params![self.user_id, query.from, query.to, query.security_code],
+            |row| {
+                Ok((
+                    (
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ),
+                    (
+                        row.get(5)?,
+                        row.get(6)?,
+                        row.get(7)?,
+                        row.get(8)?,
+                        row.get(9)?,
+                    ),
+                ))
+            },
+        )?;
+
+        let mut profit_and_loss_list = Vec::new();
+        for row in rows {
+            let (
+                (trade_date, settlement_date, security_code, security_name, account),
+                (shares, asked_price, proceeds, purchase_price, realized_profit_and_loss),
+            ) = row?;
+            let money = |amount: Option<String>| -> Result<Option<Money>> {
+                Ok(decode_decimal(amount)?.map(Money::jpy))
+            };
+
+            let mut profit_and_loss = ProfitAndLoss::new();
+            profit_and_loss.trade_date = Some(trade_date);
+            profit_and_loss.settlement_date = settlement_date;
+            profit_and_loss.security_code = security_code;
+            profit_and_loss.security_name = security_name;
+            profit_and_loss.account = account;
+            profit_and_loss.shares = shares;
+            profit_and_loss.asked_price = money(asked_price)?;
+            profit_and_loss.proceeds = money(proceeds)?;
+            profit_and_loss.purchase_price = money(purchase_price)?;
+            profit_and_loss.realized_profit_and_loss = money(realized_profit_and_loss)?;
+            profit_and_loss_list.push(profit_and_loss);
+        }
+
+        Ok(profit_and_loss_list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lib::Store;
+    use super::*;
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    fn partial_fill() -> ProfitAndLoss {
+        ProfitAndLoss {
+            trade_date: NaiveDate::from_ymd_opt(2025, 3, 3),
+            security_code: Some("7203".to_string()),
+            account: Some("特定".to_string()),
+            shares: Some(100),
+            realized_profit_and_loss: Some(Money::jpy(Decimal::from(5_000))),
+            ..ProfitAndLoss::new()
+        }
+    }
+
+    #[test]
+    fn identical_rows_in_one_file_are_all_saved() {
+        let store = Store::open(":memory:").unwrap();
+        let store = store.for_user(1);
+
+        let summary = store
+            .save_profit_and_loss(&[partial_fill(), partial_fill()])
+            .unwrap();
+        assert_eq!((summary.inserted, summary.duplicates), (2, 0));
+
+        // 同じファイルをもう一度取り込んでも増えない。1件増えたファイルなら増えた分だけ保存する
+        let summary = store
+            .save_profit_and_loss(&[partial_fill(), partial_fill()])
+            .unwrap();
+        assert_eq!((summary.inserted, summary.duplicates), (0, 2));
+        let summary = store
+            .save_profit_and_loss(&[partial_fill(), partial_fill(), partial_fill()])
+            .unwrap();
+        assert_eq!((summary.inserted, summary.duplicates), (1, 2));
+
+        let saved = store
+            .load_profit_and_loss(&HistoryQuery::default())
+            .unwrap();
+        assert_eq!(saved.len(), 3);
+    }
+}
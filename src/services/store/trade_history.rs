@@ -0,0 +1,123 @@
+use anyhow::Result;
+use rusqlite::params;
+
+use super::lib::{
+    decode_decimal, encode_decimal, HistoryQuery, ImportSummary, Occurrences, UserStore,
+};
+use crate::services::{money::Money, trade_history::trade_history::TradeHistory};
+
+impl UserStore<'_> {
+    // 約定日・銘柄・口座・取引・数量・受渡金額が以前の取込の約定と一致する約定は重複として読み飛ばす
+    pub fn save_trade_history<'a>(
+        &self,
+        trade_history: impl IntoIterator<Item = &'a TradeHistory>,
+    ) -> Result<ImportSummary> {
+        let mut connection = self.store.connection()?;
+        let transaction = connection.transaction()?;
+        let mut summary = ImportSummary::default();
+        {
+            let mut statement = transaction.prepare(
+                "INSERT OR IGNORE INTO trade_history (
+                    user_id, trade_date, settlement_date, security_code, security_name, account,
+                    trade_type, shares, unit_price, fees, settlement_amount, occurrence
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            )?;
+            let amount = |money: &Option<Money>| money.as_ref().map(|m| encode_decimal(m.amount));
+            let mut occurrences = Occurrences::new();
+            for trade in trade_history {
+                let occurrence = occurrences.next((
+                    trade.trade_date,
+                    trade.security_code.clone().unwrap_or_default(),
+                    trade.security_name.clone().unwrap_or_default(),
+                    trade.account.clone().unwrap_or_default(),
+                    trade.trade_type.clone().unwrap_or_default(),
+                    trade.shares.unwrap_or_default(),
+                    amount(&trade.settlement_amount).unwrap_or_default(),
+                ));
+                let inserted = statement.execute(params![
+                    self.user_id,
+                    trade.trade_date,
+                    trade.settlement_date,
+                    trade.security_code,
+                    trade.security_name,
+                    trade.account,
+                    trade.trade_type,
+                    trade.shares,
+                    amount(&trade.unit_price),
+                    amount(&trade.fees),
+                    amount(&trade.settlement_amount),
+                    occurrence,
+                ])?;
+                if inserted > 0 {
+                    summary.inserted += 1;
+                } else {
+                    summary.duplicates += 1;
+                }
+            }
+        }
+        transaction.commit()?;
+
+        Ok(summary)
+    }
+
+    pub fn load_trade_history(&self, query: &HistoryQuery) -> Result<Vec<TradeHistory>> {
+        let connection = self.store.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT trade_date, settlement_date, security_code, security_name, account,
+                    trade_type, shares, unit_price, fees, settlement_amount
+             FROM trade_history
+             WHERE user_id = ?1
+               AND (?2 IS NULL OR trade_date >= ?2)
+               AND (?3 IS NULL OR trade_date <= ?3)
+               AND (?4 IS NULL OR security_code = ?4)
+             ORDER BY trade_date, id",
+        )?;
+        let rows = statement.query_map(
+            params![self.user_id, query.from, query.to, query.security_code],
+            |row| {
+                Ok((
+                    (
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ),
+                    (
+                        row.get(5)?,
+                        row.get(6)?,
+                        row.get(7)?,
+                        row.get(8)?,
+                        row.get(9)?,
+                    ),
+                ))
+            },
+        )?;
+
+        let mut trade_history = Vec::new();
+        for row in rows {
+            let (
+                (trade_date, settlement_date, security_code, security_name, account),
+                (trade_type, shares, unit_price, fees, settlement_amount),
+            ) = row?;
+            let money = |amount: Option<String>| -> Result<Option<Money>> {
+                Ok(decode_decimal(amount)?.map(Money::jpy))
+            };
+
+            let mut trade = TradeHistory::new();
+            trade.trade_date = Some(trade_date);
+            trade.settlement_date = settlement_date;
+            trade.security_code = security_code;
+            trade.security_name = security_name;
+            trade.account = account;
+            trade.trade_type = trade_type;
+            trade.shares = shares;
+            trade.unit_price = money(unit_price)?;
+            trade.fees = money(fees)?;
+            trade.settlement_amount = money(settlement_amount)?;
+            trade_history.push(trade);
+        }
+
+        Ok(trade_history)
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use rusqlite::{params, ErrorCode, OptionalExtension};
+
+use super::lib::Store;
+use crate::services::auth::User;
+
+impl Store {
+    // ユーザー名が既に使われている場合はNoneを返す
+    pub fn insert_user(&self, username: &str, password_hash: &str) -> Result<Option<User>> {
+        let connection = self.connection()?;
+        let result = connection.execute(
+            "INSERT INTO users (username, password_hash) VALUES (?1, ?2)",
+            params![username, password_hash],
+        );
+        match result {
+            Ok(_) => Ok(Some(User {
+                id: connection.last_insert_rowid(),
+                username: username.to_string(),
+            })),
+            Err(rusqlite::Error::SqliteFailure(error, _))
+                if error.code == ErrorCode::ConstraintViolation =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // ユーザーと、照合用のパスワードハッシュ
+    pub fn find_user(&self, username: &str) -> Result<Option<(User, String)>> {
+        let connection = self.connection()?;
+        let user = connection
+            .query_row(
+                "SELECT id, username, password_hash FROM users WHERE username = ?1",
+                params![username],
+                |row| {
+                    let user = User {
+                        id: row.get(0)?,
+                        username: row.get(1)?,
+                    };
+                    Ok((user, row.get(2)?))
+                },
+            )
+            .optional()?;
+        Ok(user)
+    }
+}
@@ -0,0 +1,63 @@
+use crate::setting::{INCOME_TAX_RATE, RESIDENT_TAX_RATE};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::ops::{Add, Sub};
+
+// 特定口座（源泉徴収あり）で徴収される税額の内訳
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Withholding {
+    pub income_tax: Decimal,   // 所得税（復興特別所得税を含む）
+    pub resident_tax: Decimal, // 住民税
+}
+
+impl Withholding {
+    // 所得税と住民税はそれぞれ円未満を切り捨てて計算する
+    pub fn calculate(taxable_amount: Decimal) -> Self {
+        if taxable_amount <= Decimal::ZERO {
+            return Withholding::default();
+        }
+
+        Withholding {
+            income_tax: (taxable_amount * INCOME_TAX_RATE).floor(),
+            resident_tax: (taxable_amount * RESIDENT_TAX_RATE).floor(),
+        }
+    }
+
+    // 源泉徴収された税額の合計を所得税と住民税に分ける。配当の明細には合計しかないため、
+    // 住民税を税率の比で按分し（円未満は四捨五入）、残りを所得税とする
+    pub fn split(withheld: Decimal) -> Self {
+        let resident_tax =
+            (withheld * RESIDENT_TAX_RATE / (INCOME_TAX_RATE + RESIDENT_TAX_RATE)).round();
+        Withholding {
+            income_tax: withheld - resident_tax,
+            resident_tax,
+        }
+    }
+
+    pub fn total(&self) -> Decimal {
+        self.income_tax + self.resident_tax
+    }
+}
+
+impl Add for Withholding {
+    type Output = Withholding;
+
+    fn add(self, other: Withholding) -> Withholding {
+        Withholding {
+            income_tax: self.income_tax + other.income_tax,
+            resident_tax: self.resident_tax + other.resident_tax,
+        }
+    }
+}
+
+// 累計税額の差分。年初来の損失で相殺されると負（還付）になる
+impl Sub for Withholding {
+    type Output = Withholding;
+
+    fn sub(self, other: Withholding) -> Withholding {
+        Withholding {
+            income_tax: self.income_tax - other.income_tax,
+            resident_tax: self.resident_tax - other.resident_tax,
+        }
+    }
+}
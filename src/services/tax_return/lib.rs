@@ -0,0 +1,212 @@
+use super::{
+    super::dividend_list::dividend_list::DividendList,
+    super::fx_rate::fx_rate::FxRateTable,
+    super::loss_carry_forward::loss_carry_forward::{AnnualResult, LossCarryForward},
+    super::profit_and_loss::profit_and_loss::ProfitAndLoss,
+    super::store::lib::{HistoryQuery, UserStore},
+    super::templete::{TableRenderer, TableView, TemplateStruct},
+    tax_return::{TaxReturn, TaxReturnLine},
+};
+use crate::setting::HEADERS;
+use anyhow::Result;
+use chrono::{Datelike, Local};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+};
+use tera::Tera;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TaxReturnQuery {
+    pub year: Option<i32>, // 未指定なら明細のある最新の年
+}
+
+// 保存済みの明細から確定申告用の年間集計を作る
+pub struct TaxReturnManager {
+    template_struct: TemplateStruct,
+    dividend_list: RefCell<Vec<DividendList>>,
+    profit_and_loss_list: RefCell<Vec<ProfitAndLoss>>,
+    annual_results: RefCell<Vec<AnnualResult>>, // 明細から集計した年間損益と申告の有無
+}
+
+impl TaxReturnManager {
+    pub fn new() -> Self {
+        TaxReturnManager {
+            template_struct: TemplateStruct::new(),
+            dividend_list: RefCell::new(Vec::new()),
+            profit_and_loss_list: RefCell::new(Vec::new()),
+            annual_results: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn load_details(&self, store: &UserStore) -> Result<()> {
+        let query = HistoryQuery::default();
+        // 外貨建ての配当を円で集計するため、配当の明細と同じレートで円換算額を求めておく
+        let fx_rates = FxRateTable::new(&store.load_fx_rates(&query)?);
+        *self.dividend_list.borrow_mut() = store
+            .load_dividend_list(&query)?
+            .into_iter()
+            .map(|dividend| dividend.with_jpy_amounts(&fx_rates))
+            .collect();
+        *self.profit_and_loss_list.borrow_mut() = store.load_profit_and_loss(&query)?;
+        Ok(())
+    }
+
+    // 明細のある年ごとの年間損益（繰越控除前）
+    fn calculate_annual_results(&self) -> Vec<AnnualResult> {
+        self.years()
+            .into_iter()
+            .map(|year| self.tax_return_without_carry_forward(year).annual_result())
+            .collect()
+    }
+
+    // 年間損益の元になる明細を保存したあとに呼び、年間損益を集計し直して保存する
+    pub fn save_annual_results(store: &UserStore) -> Result<()> {
+        let manager = Self::new();
+        manager.load_details(store)?;
+        store.save_annual_results(&manager.calculate_annual_results())
+    }
+
+    // 年間損益は保存済みのものを使う。年間損益を保存する前に取り込んだ明細の年は、その場で集計する。
+    // 明細のない年も、申告の有無を記録していれば残す
+    pub fn load(&self, store: &UserStore) -> Result<()> {
+        self.load_details(store)?;
+
+        let filings = store.load_filings()?;
+        let mut results = filings
+            .iter()
+            .map(|(&year, &filed)| {
+                let result = AnnualResult {
+                    year,
+                    transfer_income: Decimal::ZERO,
+                    dividend_income: Decimal::ZERO,
+                    filed,
+                };
+                (year, result)
+            })
+            .collect::<BTreeMap<_, _>>();
+        let stored = store.load_annual_results()?;
+        let stored_years = stored
+            .iter()
+            .map(|result| result.year)
+            .collect::<BTreeSet<_>>();
+        for result in stored {
+            results.insert(result.year, result);
+        }
+        for calculated in self.calculate_annual_results() {
+            if stored_years.contains(&calculated.year) {
+                continue;
+            }
+            let filed = filings
+                .get(&calculated.year)
+                .copied()
+                .unwrap_or(calculated.filed);
+            results.insert(
+                calculated.year,
+                AnnualResult {
+                    filed,
+                    ..calculated
+                },
+            );
+        }
+        *self.annual_results.borrow_mut() = results.into_values().collect();
+        Ok(())
+    }
+
+    pub fn annual_results(&self) -> Vec<AnnualResult> {
+        self.annual_results.borrow().clone()
+    }
+
+    pub fn loss_carry_forwards(&self) -> Vec<LossCarryForward> {
+        LossCarryForward::calculate(&self.annual_results.borrow())
+    }
+
+    // 明細のある年（新しい順）
+    pub fn years(&self) -> Vec<i32> {
+        let dividend_list = self.dividend_list.borrow();
+        let profit_and_loss_list = self.profit_and_loss_list.borrow();
+        let years = dividend_list
+            .iter()
+            .filter_map(|dividend| dividend.settlement_date)
+            .chain(
+                profit_and_loss_list
+                    .iter()
+                    .filter_map(|profit_and_loss| profit_and_loss.trade_date),
+            )
+            .map(|date| date.year())
+            .collect::<BTreeSet<_>>();
+        years.into_iter().rev().collect()
+    }
+
+    // 明細がなければ、申告の対象になる前年とする
+    pub fn resolve_year(&self, query: &TaxReturnQuery) -> i32 {
+        query
+            .year
+            .or_else(|| self.years().first().copied())
+            .unwrap_or_else(|| Local::now().year() - 1)
+    }
+
+    pub fn tax_return(&self, year: i32) -> TaxReturn {
+        let loss_carry_forwards = self.loss_carry_forwards();
+        TaxReturn::new(
+            year,
+            &self.dividend_list.borrow(),
+            &self.profit_and_loss_list.borrow(),
+            loss_carry_forwards.iter().find(|c| c.year == year),
+        )
+    }
+
+    fn tax_return_without_carry_forward(&self, year: i32) -> TaxReturn {
+        TaxReturn::new(
+            year,
+            &self.dividend_list.borrow(),
+            &self.profit_and_loss_list.borrow(),
+            None,
+        )
+    }
+
+    pub fn generate_html_table(&self, year: i32, tera: &Tera) -> Result<String> {
+        let headers = self.generate_table_header(TaxReturnLine::new().get_all_fields());
+        let rows = self
+            .tax_return(year)
+            .lines
+            .iter()
+            .map(|line| self.generate_table_row(&line.get_all_fields()))
+            .collect();
+
+        let table = TableView { headers, rows };
+        self.render_table(tera, "partials/tax_return_table.html", &table)
+    }
+
+    pub fn generate_json(&self, year: i32) -> Value {
+        json!(self.tax_return(year))
+    }
+
+    // 表計算ソフトでそのまま開けるよう、BOM付きUTF-8で書き出す
+    pub fn generate_csv(&self, year: i32) -> Result<Vec<u8>> {
+        let mut writer = csv::Writer::from_writer(b"\xEF\xBB\xBF".to_vec());
+        let headers = TaxReturnLine::new()
+            .get_all_fields()
+            .into_iter()
+            .map(|(key, _)| HEADERS.get(&key).unwrap().to_string())
+            .collect::<Vec<_>>();
+        writer.write_record(&headers)?;
+        for line in self.tax_return(year).lines {
+            writer.write_record(
+                line.get_all_fields()
+                    .into_iter()
+                    .map(|(_, value)| value.unwrap_or_default()),
+            )?;
+        }
+        Ok(writer.into_inner()?)
+    }
+}
+
+impl TableRenderer for TaxReturnManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
+    }
+}
@@ -0,0 +1,320 @@
+use chrono::Datelike;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::services::{
+    account::AccountTotals,
+    dividend_list::dividend_list::DividendList,
+    loss_carry_forward::loss_carry_forward::{AnnualResult, LossCarryForward},
+    money::Money,
+    profit_and_loss::profit_and_loss::ProfitAndLoss,
+    tax::Withholding,
+};
+
+// 申告書に転記する1項目。口座ごとの内訳がない項目は合計欄のみを埋める
+#[derive(Debug, Clone, Serialize)]
+pub struct TaxReturnLine {
+    pub section: String,                 // 区分
+    pub item: String,                    // 項目
+    pub specific_account: Option<Money>, // 特定口座
+    pub general_account: Option<Money>,  // 一般口座
+    pub total: Option<Money>,            // 合計
+}
+
+impl TaxReturnLine {
+    pub fn new() -> Self {
+        TaxReturnLine {
+            section: String::new(),
+            item: String::new(),
+            specific_account: None,
+            general_account: None,
+            total: None,
+        }
+    }
+
+    fn by_account(section: &str, item: &str, amounts: AccountTotals) -> Self {
+        TaxReturnLine {
+            section: section.to_string(),
+            item: item.to_string(),
+            specific_account: Some(Money::jpy(amounts.specific)),
+            general_account: Some(Money::jpy(amounts.general)),
+            total: Some(Money::jpy(amounts.taxable())),
+        }
+    }
+
+    fn total_only(section: &str, item: &str, total: Decimal) -> Self {
+        TaxReturnLine {
+            section: section.to_string(),
+            item: item.to_string(),
+            specific_account: None,
+            general_account: None,
+            total: Some(Money::jpy(total)),
+        }
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        let money = |m: &Option<Money>| m.as_ref().map(|m| m.to_string());
+        vec![
+            ("section".to_string(), Some(self.section.clone())),
+            ("item".to_string(), Some(self.item.clone())),
+            (
+                "specific_account".to_string(),
+                money(&self.specific_account),
+            ),
+            ("general_account".to_string(), money(&self.general_account)),
+            ("total".to_string(), money(&self.total)),
+        ]
+    }
+}
+
+// 確定申告書第三表（分離課税用）と付表（上場株式等に係る譲渡損失の損益通算及び繰越控除用）に
+// 転記する年間の数値
+#[derive(Debug, Clone, Serialize)]
+pub struct TaxReturn {
+    pub year: i32,
+    pub transfer_income: Decimal, // 課税口座の譲渡所得等
+    pub dividend_income: Decimal, // 課税口座の配当所得
+    pub lines: Vec<TaxReturnLine>,
+}
+
+impl TaxReturn {
+    // 繰越控除の計算結果がなければ、その年の中だけで損益通算する
+    pub fn new(
+        year: i32,
+        dividend_list: &[DividendList],
+        profit_and_loss_list: &[ProfitAndLoss],
+        loss_carry_forward: Option<&LossCarryForward>,
+    ) -> Self {
+        let mut proceeds = AccountTotals::default();
+        let mut acquisition_costs = AccountTotals::default();
+        let mut realized = AccountTotals::default();
+        let mut nisa_realized = Decimal::ZERO;
+        for profit_and_loss in profit_and_loss_list
+            .iter()
+            .filter(|p| p.trade_date.is_some_and(|date| date.year() == year))
+        {
+            let account_type = profit_and_loss.account_type();
+            let proceed = profit_and_loss.proceeds.as_ref().map(|p| p.amount);
+            let gain = profit_and_loss
+                .realized_profit_and_loss
+                .as_ref()
+                .map(|p| p.amount);
+            // NISA口座は申告不要のため参考値としてのみ扱う
+            if !account_type.is_taxable() {
+                nisa_realized += gain.unwrap_or_default();
+                continue;
+            }
+            proceeds.add(account_type, proceed.unwrap_or_default());
+            realized.add(account_type, gain.unwrap_or_default());
+            // 取得費と譲渡費用（手数料等）は、売却額と実現損益の差として求める
+            if let (Some(proceed), Some(gain)) = (proceed, gain) {
+                acquisition_costs.add(account_type, proceed - gain);
+            }
+        }
+
+        // 外貨建ての配当は入金日のレートで円に換算する。レートがなく換算できない明細は件数を参考として出す
+        let mut dividends = AccountTotals::default();
+        let mut dividend_taxes = AccountTotals::default();
+        let mut dividend_income_taxes = AccountTotals::default();
+        let mut dividend_resident_taxes = AccountTotals::default();
+        let mut unconverted_dividends = 0;
+        for dividend in dividend_list
+            .iter()
+            .filter(|d| d.settlement_date.is_some_and(|date| date.year() == year))
+        {
+            let account_type = dividend.account_type();
+            let Some(before_tax) = dividend.to_jpy(&dividend.dividends_before_tax) else {
+                if dividend.dividends_before_tax.is_some() {
+                    unconverted_dividends += 1;
+                }
+                continue;
+            };
+            // 支払通知書の金額と合わせるため、明細の税額（外国源泉税を除いた国内の源泉徴収税額）を合計する。
+            // 内訳の分からない明細は税額をそのまま使う
+            let taxes = dividend
+                .to_jpy(&dividend.domestic_taxes)
+                .or_else(|| dividend.to_jpy(&dividend.taxes))
+                .unwrap_or_default();
+            dividends.add(account_type, before_tax);
+            dividend_taxes.add(account_type, taxes);
+            let withholding = Withholding::split(taxes);
+            dividend_income_taxes.add(account_type, withholding.income_tax);
+            dividend_resident_taxes.add(account_type, withholding.resident_tax);
+        }
+
+        let specific_withholding = Withholding::calculate(realized.specific);
+        let withheld_income_taxes = AccountTotals {
+            specific: specific_withholding.income_tax,
+            ..AccountTotals::default()
+        };
+        let withheld_resident_taxes = AccountTotals {
+            specific: specific_withholding.resident_tax,
+            ..AccountTotals::default()
+        };
+
+        // 譲渡損失は申告分離課税の配当所得と損益通算でき、引ききれない分は翌年以後に繰り越せる
+        let netted_income = realized.taxable() + dividends.taxable();
+        let amount = |money: &Option<Money>| money.as_ref().map_or(Decimal::ZERO, |m| m.amount);
+        let (carried_in_loss, used_loss, carried_forward_loss) = match loss_carry_forward {
+            Some(carry_forward) => (
+                amount(&carry_forward.carried_in_loss) - amount(&carry_forward.expired_loss),
+                amount(&carry_forward.used_loss),
+                amount(&carry_forward.carried_out_loss),
+            ),
+            None => (
+                Decimal::ZERO,
+                Decimal::ZERO,
+                (-netted_income).max(Decimal::ZERO),
+            ),
+        };
+
+        let transfer = "譲渡所得（付表）";
+        let dividend = "配当所得（第三表）";
+        let separate = "分離課税（第三表）";
+        let mut lines = vec![
+            TaxReturnLine::by_account(transfer, "譲渡による収入金額", proceeds),
+            TaxReturnLine::by_account(transfer, "取得費・譲渡費用", acquisition_costs),
+            TaxReturnLine::by_account(transfer, "差引金額（譲渡所得等の金額）", realized),
+            TaxReturnLine::by_account(transfer, "源泉徴収税額（所得税）", withheld_income_taxes),
+            TaxReturnLine::by_account(transfer, "源泉徴収税額（住民税）", withheld_resident_taxes),
+            TaxReturnLine::by_account(dividend, "上場株式等の配当等の収入金額", dividends),
+            TaxReturnLine::by_account(
+                dividend,
+                "源泉徴収税額（所得税及び復興特別所得税）",
+                dividend_income_taxes,
+            ),
+            TaxReturnLine::by_account(
+                dividend,
+                "配当割額控除額（住民税）",
+                dividend_resident_taxes,
+            ),
+            TaxReturnLine::by_account(dividend, "源泉徴収税額（明細の合計）", dividend_taxes),
+            TaxReturnLine::total_only(
+                separate,
+                "損益通算後の所得金額",
+                netted_income.max(Decimal::ZERO),
+            ),
+            TaxReturnLine::total_only(separate, "前年から繰り越された損失額", carried_in_loss),
+            TaxReturnLine::total_only(separate, "本年分で差し引く損失額", used_loss),
+            TaxReturnLine::total_only(
+                separate,
+                "繰越控除後の所得金額",
+                (netted_income - used_loss).max(Decimal::ZERO),
+            ),
+            TaxReturnLine::total_only(
+                separate,
+                "翌年以後に繰り越される損失額",
+                carried_forward_loss,
+            ),
+            TaxReturnLine::total_only("参考", "NISA口座の譲渡損益（申告不要）", nisa_realized),
+        ];
+        if unconverted_dividends > 0 {
+            lines.push(TaxReturnLine {
+                section: "参考".to_string(),
+                item: format!(
+                    "為替レートがなく円換算できない外貨建ての配当 {unconverted_dividends}件（上記に含まない）"
+                ),
+                ..TaxReturnLine::new()
+            });
+        }
+
+        TaxReturn {
+            year,
+            transfer_income: realized.taxable(),
+            dividend_income: dividends.taxable(),
+            lines,
+        }
+    }
+
+    pub fn annual_result(&self) -> AnnualResult {
+        AnnualResult {
+            year: self.year,
+            transfer_income: self.transfer_income,
+            dividend_income: self.dividend_income,
+            filed: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::money::Currency;
+    use chrono::NaiveDate;
+
+    fn dividend(before_tax: i64, taxes: i64) -> DividendList {
+        DividendList {
+            settlement_date: NaiveDate::from_ymd_opt(2025, 6, 1),
+            account: Some("特定口座".to_string()),
+            currency: Some("日本円".to_string()),
+            dividends_before_tax: Some(Money::jpy(Decimal::from(before_tax))),
+            taxes: Some(Money::jpy(Decimal::from(taxes))),
+            ..DividendList::new()
+        }
+        .with_tax_breakdown()
+    }
+
+    fn specific_account(tax_return: &TaxReturn, item: &str) -> Option<Decimal> {
+        tax_return
+            .lines
+            .iter()
+            .find(|line| line.item == item)
+            .and_then(|line| line.specific_account.as_ref())
+            .map(|money| money.amount)
+    }
+
+    #[test]
+    fn dividend_withholding_sums_the_withheld_taxes() {
+        // 2件目は支払時に年内の譲渡損失と通算され、源泉徴収税額が少ない
+        let dividend_list = [dividend(10_000, 2_031), dividend(10_000, 1_000)];
+        let tax_return = TaxReturn::new(2025, &dividend_list, &[], None);
+
+        assert_eq!(
+            specific_account(&tax_return, "源泉徴収税額（明細の合計）"),
+            Some(Decimal::from(3_031))
+        );
+        let income_tax = specific_account(&tax_return, "源泉徴収税額（所得税及び復興特別所得税）");
+        let resident_tax = specific_account(&tax_return, "配当割額控除額（住民税）");
+        assert_eq!(income_tax, Some(Decimal::from(2_285)));
+        assert_eq!(resident_tax, Some(Decimal::from(746)));
+    }
+
+    #[test]
+    fn foreign_dividends_are_converted_at_the_dividend_rate() {
+        let usd = |amount: &str| {
+            Some(Money::new(
+                amount.parse().unwrap(),
+                Currency::from_label("USD"),
+            ))
+        };
+        let foreign = DividendList {
+            currency: Some("USドル".to_string()),
+            dividends_before_tax: usd("100"),
+            taxes: usd("30.31"),
+            foreign_taxes: None,
+            domestic_taxes: None,
+            fx_rate: Some(Decimal::from(150)),
+            ..dividend(0, 0)
+        }
+        .with_tax_breakdown();
+        let unconverted = DividendList {
+            fx_rate: None,
+            ..foreign.clone()
+        };
+        let tax_return = TaxReturn::new(2025, &[foreign, unconverted], &[], None);
+
+        // 100ドル × 150円。国内の源泉徴収税額は外国源泉税10ドルを除いた20.31ドル × 150円
+        assert_eq!(
+            specific_account(&tax_return, "上場株式等の配当等の収入金額"),
+            Some(Decimal::from(15_000))
+        );
+        assert_eq!(
+            specific_account(&tax_return, "源泉徴収税額（明細の合計）"),
+            Some(Decimal::from(3_046))
+        );
+        assert!(tax_return
+            .lines
+            .iter()
+            .any(|line| line.item.contains("円換算できない外貨建ての配当 1件")));
+    }
+}
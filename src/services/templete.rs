@@ -1,93 +1,211 @@
-use super::csv::lib::CSVAccessor;
+use super::{
+    broker::lib::{self as broker, BrokerAdapter},
+    common::ParseDiagnostic,
+    csv::{
+        column_map::{ColumnMap, ColumnSpec},
+        lib::{CSVAccessor, CSVData, PreambleEntry},
+    },
+    grouping::Grouping,
+    store::lib::{HistoryQuery, ImportSummary, UserStore},
+    tax_return::lib::TaxReturnManager,
+};
 use crate::setting::HEADERS;
 use anyhow::Result;
 use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use tera::{Context, Tera};
 
-pub struct TemplateStruct;
+// クエリパラメータで指定する読み込みオプション
+#[derive(Debug, Default, Deserialize)]
+pub struct ReportOptions {
+    pub encoding: Option<String>, // 文字コードの明示指定（未指定なら自動判定）
+    pub broker: Option<String>,   // 証券会社の明示指定（未指定ならヘッダー行から自動判定）
+    pub group: Option<Grouping>,  // 小計を出す単位（未指定なら表ごとの既定値）
+}
+
+// 読み込んだCSVファイル自体の情報
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ReportMetadata {
+    pub broker: String,
+    pub encoding: String,
+    pub preamble: Vec<PreambleEntry>, // ヘッダー行より前の付帯情報（期間・出力日時・口座など）
+    pub ignored_columns: Vec<String>, // 読み飛ばした列のヘッダー
+    pub stored: Option<ImportSummary>, // 保存した件数（保存済みデータの表示時はNone）
+}
+
+pub struct TemplateStruct {
+    pub broker: Cell<Option<&'static dyn BrokerAdapter>>, // Noneなら読み込み時に自動判定
+    pub metadata: RefCell<ReportMetadata>,
+    pub diagnostics: RefCell<Vec<ParseDiagnostic>>, // 読み込み時の警告
+    pub grouping: Cell<Option<Grouping>>,           // Noneなら表ごとの既定の単位でまとめる
+}
 
 impl TemplateStruct {
     pub fn new() -> TemplateStruct {
-        TemplateStruct {}
+        TemplateStruct {
+            broker: Cell::new(None),
+            metadata: RefCell::new(ReportMetadata::default()),
+            diagnostics: RefCell::new(Vec::new()),
+            grouping: Cell::new(None),
+        }
     }
 }
 
-pub trait TemplateManager {
-    fn execute(&self, bytes: Vec<u8>) -> Result<String> {
-        let records = self.read_csv_data(bytes)?;
-        self.process_records(records);
-        self.generate_html_table()
-    }
+#[derive(Debug, Serialize)]
+pub struct HeaderView {
+    pub key: String,   // CSSクラス兼フィールド名
+    pub label: String, // 表示名
+}
+
+#[derive(Debug, Serialize)]
+pub struct CellView {
+    pub class: String,
+    pub value: String, // 整形済みの表示値（エスケープはTera側で行う）
+}
+
+#[derive(Debug, Serialize)]
+pub struct RowView {
+    pub class: String,
+    pub cells: Vec<CellView>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TableView {
+    pub headers: Vec<HeaderView>,
+    pub rows: Vec<RowView>,
+}
 
-    fn read_csv_data(&self, bytes: Vec<u8>) -> Result<Vec<StringRecord>> {
-        CSVAccessor::read(bytes)
+// 表の組み立てとTeraでの描画を担う。CSV1つに対応しないレポートもこれを使う
+pub trait TableRenderer {
+    fn template_struct(&self) -> &TemplateStruct;
+
+    fn render_table(&self, tera: &Tera, template: &str, table: &TableView) -> Result<String> {
+        let mut context = Context::new();
+        context.insert("table", table);
+        self.render_context(tera, template, context)
     }
 
-    fn process_records(&self, records: Vec<StringRecord>);
-    fn generate_html_table(&self) -> Result<String>;
+    // 表が複数あるなど、table以外の値も渡すテンプレート用
+    fn render_context(&self, tera: &Tera, template: &str, mut context: Context) -> Result<String> {
+        context.insert("metadata", &*self.template_struct().metadata.borrow());
+        context.insert("warnings", &*self.template_struct().diagnostics.borrow());
+        Ok(tera.render(template, &context)?)
+    }
 
-    fn generate_table_header(&self, headers: Vec<(String, Option<String>)>) -> String {
-        let mut table = "<table><thead><tr>".to_string();
-        for (header, _) in headers {
-            let header_name = HEADERS.get(&header).unwrap();
-            table.push_str(&format!("<th class=\"{header}\">{header_name}</th>"));
-        }
-        table.push_str("</tr></thead>");
-        table
+    fn generate_table_header(&self, headers: Vec<(String, Option<String>)>) -> Vec<HeaderView> {
+        headers
+            .into_iter()
+            .map(|(header, _)| {
+                let label = HEADERS.get(&header).unwrap().to_string();
+                HeaderView { key: header, label }
+            })
+            .collect()
     }
 
     fn generate_table_row_with_class(
         &self,
         tr_class: &str,
         values: &[(String, Option<String>)],
-    ) -> String {
-        let format_class = |class: &str| {
-            if class.is_empty() {
-                String::new()
-            } else {
-                format!("class=\"{class}\"")
-            }
-        };
-
-        let tr_class = format_class(tr_class);
-        let row_content = values
+    ) -> RowView {
+        let cells = values
             .iter()
             .map(|(key, value)| {
                 let value = value.as_deref().unwrap_or("");
                 let value = self.format_value(key, value);
-                let key = if value.starts_with("-") {
+                let class = if value.starts_with("-") {
                     format!("{key} negative")
                 } else {
                     key.to_string()
                 };
-                format!("<td {}>{value}</td>", format_class(&key))
+                CellView { class, value }
             })
-            .collect::<String>();
+            .collect();
 
-        format!("<tr {tr_class}>{row_content}</tr>")
+        RowView {
+            class: tr_class.to_string(),
+            cells,
+        }
     }
 
     fn format_value(&self, key: &str, value: &str) -> String {
         match key {
-            "settlement_date" | "trade_date" => self.format_date(value),
-            "asked_price"
+            "fx_rate_date"
+            | "last_settlement_date"
+            | "payment_date"
+            | "rate_date"
+            | "settlement_date"
+            | "trade_date" => self.format_date(value),
+            "acquisition_amount"
+            | "acquisition_price"
+            | "annual_limit"
+            | "annual_remaining"
+            | "annual_used"
+            | "asked_price"
+            | "average_cost"
+            | "carried_in_loss"
+            | "carried_out_loss"
+            | "cost_per_share"
+            | "cumulative_realized_profit_and_loss"
+            | "current_price"
+            | "cumulative_withholding_tax"
+            | "difference"
+            | "dividend_growth_rate"
+            | "dividend_income"
+            | "dividend_per_share"
             | "dividends_before_tax"
+            | "domestic_taxes"
+            | "expired_loss"
+            | "expected_refund"
+            | "foreign_taxes"
+            | "fx_rate"
+            | "general_account"
+            | "holding_shares"
+            | "income_tax"
+            | "jpy_dividends_before_tax"
+            | "jpy_foreign_taxes"
+            | "jpy_net_amount_received"
+            | "jpy_taxes"
+            | "lifetime_limit"
+            | "lifetime_remaining"
+            | "lifetime_used"
             | "net_amount_received"
+            | "netted_income"
+            | "netted_withholding_tax"
+            | "new_loss"
             | "proceeds"
             | "profit_and_loss"
+            | "projected_dividend"
             | "purchase_price"
             | "realized_profit_and_loss"
+            | "recomputed_profit_and_loss"
+            | "remaining_loss"
+            | "resident_tax"
+            | "restored_capacity"
+            | "settlement_amount"
             | "shares"
+            | "specific_account"
+            | "taxable_income"
             | "taxes"
+            | "total"
             | "total_dividends_before_tax"
             | "total_net_amount_received"
             | "total_realized_profit_and_loss"
             | "total_taxes"
-            | "withholding_tax" => self.format_number(value),
+            | "transfer_income"
+            | "ttm"
+            | "unrealized_profit_and_loss"
+            | "unrealized_profit_and_loss_rate"
+            | "used_loss"
+            | "valuation"
+            | "withholding_tax"
+            | "yield_on_cost" => self.format_number(value),
             _ => value.to_string(),
         }
     }
 
-    fn generate_table_row(&self, values: &[(String, Option<String>)]) -> String {
+    fn generate_table_row(&self, values: &[(String, Option<String>)]) -> RowView {
         self.generate_table_row_with_class("", values)
     }
 
@@ -119,3 +237,132 @@ pub trait TemplateManager {
         }
     }
 }
+
+pub trait TemplateManager: TableRenderer {
+    fn execute(
+        &self,
+        bytes: Vec<u8>,
+        options: &ReportOptions,
+        store: &UserStore,
+        tera: &Tera,
+    ) -> Result<String> {
+        self.import(bytes, options)?;
+        self.persist(store)?;
+        self.generate_html_table(tera)
+    }
+
+    fn execute_json(
+        &self,
+        bytes: Vec<u8>,
+        options: &ReportOptions,
+        store: &UserStore,
+    ) -> Result<Value> {
+        self.import(bytes, options)?;
+        self.persist(store)?;
+        self.generate_report_json()
+    }
+
+    // 過去に取り込んで保存済みの明細から表を作る
+    fn history(&self, store: &UserStore, query: &HistoryQuery, tera: &Tera) -> Result<String> {
+        self.load(store, query)?;
+        self.generate_html_table(tera)
+    }
+
+    fn history_json(&self, store: &UserStore, query: &HistoryQuery) -> Result<Value> {
+        self.load(store, query)?;
+        self.generate_report_json()
+    }
+
+    fn generate_report_json(&self) -> Result<Value> {
+        let mut json = self.generate_json()?;
+        json["metadata"] = serde_json::to_value(&*self.template_struct().metadata.borrow())?;
+        json["warnings"] = serde_json::to_value(&*self.template_struct().diagnostics.borrow())?;
+        Ok(json)
+    }
+
+    fn persist(&self, store: &UserStore) -> Result<()> {
+        // 証券会社を判定できずに何も読み込まなかった場合は保存しない
+        if self.template_struct().broker.get().is_none() {
+            return Ok(());
+        }
+        let summary = self.save(store)?;
+        if self.updates_annual_results() {
+            TaxReturnManager::save_annual_results(store)?;
+        }
+        self.template_struct().metadata.borrow_mut().stored = Some(summary);
+        Ok(())
+    }
+
+    // 年間損益の元になる明細（配当・実現損益・為替レート）は、保存後に年間損益を集計し直す
+    fn updates_annual_results(&self) -> bool {
+        false
+    }
+
+    // 自動判定した証券会社も記録しておき、保存時に使う
+    fn import(&self, bytes: Vec<u8>, options: &ReportOptions) -> Result<()> {
+        let Some((broker, data)) = self.read_csv_data(bytes, options)? else {
+            return Ok(());
+        };
+        self.template_struct().broker.set(Some(broker));
+        let mut columns = ColumnMap::new(self.column_specs(broker), &data.headers)?;
+        if let Some(account_type) = broker.default_account_type() {
+            columns = columns.with_default("account", account_type.label());
+        }
+
+        let mut metadata = self.template_struct().metadata.borrow_mut();
+        if self.is_broker_specific() {
+            metadata.broker = broker.name().to_string();
+        }
+        metadata.encoding = data.encoding;
+        metadata.preamble = data.preamble;
+        metadata.ignored_columns = columns.ignored.clone();
+        drop(metadata);
+
+        self.process_records(data.records, &columns);
+        Ok(())
+    }
+
+    fn read_csv_data(
+        &self,
+        bytes: Vec<u8>,
+        options: &ReportOptions,
+    ) -> Result<Option<(&'static dyn BrokerAdapter, CSVData)>> {
+        let rows = CSVAccessor::read(bytes, options.encoding.as_deref())?;
+        let broker = match self.template_struct().broker.get() {
+            Some(broker) => broker,
+            None => match broker::detect_broker(&rows, |broker| self.column_specs(broker)) {
+                Ok(broker) => broker,
+                // ヘッダー行から証券会社を決められない場合は読み込まず、brokerの指定を求める
+                Err(candidates) => {
+                    self.template_struct()
+                        .diagnostics
+                        .borrow_mut()
+                        .push(ParseDiagnostic {
+                            line: 0,
+                            column: String::new(),
+                            value: candidates
+                                .iter()
+                                .map(|broker| broker.id())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                            reason: "File skipped: header row matches several brokers equally; specify broker".to_string(),
+                        });
+                    return Ok(None);
+                }
+            },
+        };
+        Ok(Some((broker, rows.into_data(self.column_specs(broker)))))
+    }
+
+    // 為替レートのように証券会社によらない形式のCSVは、判定した証券会社を表示しない
+    fn is_broker_specific(&self) -> bool {
+        true
+    }
+
+    fn column_specs(&self, broker: &dyn BrokerAdapter) -> &'static [ColumnSpec];
+    fn process_records(&self, records: Vec<StringRecord>, columns: &ColumnMap);
+    fn save(&self, store: &UserStore) -> Result<ImportSummary>;
+    fn load(&self, store: &UserStore, query: &HistoryQuery) -> Result<()>;
+    fn generate_html_table(&self, tera: &Tera) -> Result<String>;
+    fn generate_json(&self) -> Result<Value>;
+}
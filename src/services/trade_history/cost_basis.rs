@@ -0,0 +1,519 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::services::{
+    account::AccountType,
+    money::Money,
+    profit_and_loss::profit_and_loss::ProfitAndLoss,
+    trade_history::trade_history::{TradeHistory, TradeSide},
+};
+
+// 証券会社の実現損益の明細との照合結果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStatus {
+    Purchase,             // 買付（照合の対象外）
+    Matched,              // 一致
+    Mismatched,           // 不一致
+    MissingProfitAndLoss, // 対応する実現損益の明細がない
+    InsufficientHoldings, // 売却数量に対して買付の約定が足りない（取込漏れ）
+}
+
+impl MatchStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MatchStatus::Purchase => "",
+            MatchStatus::Matched => "一致",
+            MatchStatus::Mismatched => "不一致",
+            MatchStatus::MissingProfitAndLoss => "実現損益の明細なし",
+            MatchStatus::InsufficientHoldings => "買付の約定が不足",
+        }
+    }
+}
+
+// 約定1件ごとの、再計算した取得価額と照合結果
+#[derive(Debug, Clone, Serialize)]
+pub struct CostBasis {
+    pub trade_date: Option<NaiveDate>,             // 約定日
+    pub security_code: Option<String>,             // 銘柄コード
+    pub security_name: Option<String>,             // 銘柄名
+    pub account: Option<String>,                   // 口座
+    pub trade_type: Option<String>,                // 取引
+    pub shares: Option<i32>,                       // 数量[株]
+    pub settlement_amount: Option<Money>,          // 受渡金額
+    pub holding_shares: Option<i64>,               // 約定後の保有数量
+    pub average_cost: Option<Money>,               // 平均取得単価（再計算）
+    pub purchase_price: Option<Money>,             // 平均取得価額（明細）
+    pub recomputed_profit_and_loss: Option<Money>, // 実現損益（再計算）
+    pub realized_profit_and_loss: Option<Money>,   // 実現損益（明細）
+    pub difference: Option<Money>,                 // 差額（明細 - 再計算）
+    pub status: MatchStatus,                       // 照合結果
+    #[serde(skip)]
+    price_units: Decimal,        // 平均取得単価が何口あたりの価格か
+}
+
+// 約定履歴から求めた銘柄ごとの現在の保有状況
+#[derive(Debug, Clone, Copy)]
+pub struct Position {
+    pub shares: Decimal,       // 保有数量
+    pub average_cost: Decimal, // 平均取得単価
+}
+
+// 銘柄・口座ごとの保有数量と取得価額の合計
+#[derive(Debug, Clone, Copy)]
+struct Holding {
+    shares: Decimal,
+    cost: Decimal,
+    price_units: Decimal, // 平均取得単価が何口あたりの価格か
+}
+
+impl Holding {
+    fn new(price_units: Decimal) -> Self {
+        Holding {
+            shares: Decimal::ZERO,
+            cost: Decimal::ZERO,
+            price_units,
+        }
+    }
+
+    // 株式は1株あたり、投資信託は1万口あたりの単価で、1円未満の端数は切り上げる
+    fn average_cost(&self) -> Decimal {
+        if self.shares.is_zero() {
+            Decimal::ZERO
+        } else {
+            (self.cost / self.shares * self.price_units).ceil()
+        }
+    }
+
+    // 平均取得単価で求めた、売却した数量の取得価額
+    fn cost_of(&self, average_cost: Decimal, shares: Decimal) -> Decimal {
+        average_cost * shares / self.price_units
+    }
+}
+
+impl CostBasis {
+    pub fn new() -> Self {
+        CostBasis {
+            trade_date: None,
+            security_code: None,
+            security_name: None,
+            account: None,
+            trade_type: None,
+            shares: None,
+            settlement_amount: None,
+            holding_shares: None,
+            average_cost: None,
+            purchase_price: None,
+            recomputed_profit_and_loss: None,
+            realized_profit_and_loss: None,
+            difference: None,
+            status: MatchStatus::Purchase,
+            price_units: Decimal::ONE,
+        }
+    }
+
+    fn from_trade(trade: &TradeHistory, holding: &Holding, status: MatchStatus) -> Self {
+        CostBasis {
+            trade_date: trade.trade_date,
+            security_code: trade.security_code.clone(),
+            security_name: trade.security_name.clone(),
+            account: trade.account.clone(),
+            trade_type: trade.trade_type.clone(),
+            shares: trade.shares,
+            settlement_amount: trade.settlement_amount.clone(),
+            holding_shares: i64::try_from(holding.shares).ok(),
+            average_cost: Some(Money::jpy(holding.average_cost())),
+            status,
+            price_units: holding.price_units,
+            ..Self::new()
+        }
+    }
+
+    // 銘柄・口座ごとに約定日順に移動平均で取得価額を計算する（総平均法に準ずる方法）。
+    // 買付のたびに手数料を含めた取得価額の合計を保有数量で割り直し、売却した分はその単価で取り崩す
+    pub fn calculate(
+        trade_history: &[TradeHistory],
+        profit_and_loss_list: &[ProfitAndLoss],
+    ) -> Vec<Self> {
+        let mut groups: BTreeMap<(String, AccountType), Vec<&TradeHistory>> = BTreeMap::new();
+        for trade in trade_history.iter().filter(|trade| trade.side().is_some()) {
+            groups
+                .entry((trade.security_key(), trade.account_type()))
+                .or_default()
+                .push(trade);
+        }
+
+        let mut matched = vec![false; profit_and_loss_list.len()];
+        let mut rows = Vec::new();
+        for trades in groups.values_mut() {
+            // 楽天証券のCSVは新しい約定が先に並ぶため、同じ日の約定は買付を売却より先に計上する
+            trades.sort_by_key(|trade| (trade.trade_date, trade.side() != Some(TradeSide::Buy)));
+            // 同じ銘柄の約定のどれかが投資信託の取引なら、その銘柄は投資信託とする
+            let price_units = trades
+                .iter()
+                .map(|trade| trade.price_units())
+                .max()
+                .unwrap_or(Decimal::ONE);
+            let mut holding = Holding::new(price_units);
+            for trade in trades.iter() {
+                let shares = Decimal::from(trade.shares.unwrap_or_default());
+                let amount = trade
+                    .settlement_amount
+                    .as_ref()
+                    .map(|amount| amount.amount.abs())
+                    .or_else(|| trade.trade_amount())
+                    .unwrap_or_default();
+                match trade.side() {
+                    Some(TradeSide::Buy) => {
+                        holding.shares += shares;
+                        holding.cost += amount;
+                        rows.push(Self::from_trade(trade, &holding, MatchStatus::Purchase));
+                    }
+                    Some(TradeSide::Sell) if shares > holding.shares => {
+                        holding = Holding::new(price_units);
+                        let mut row =
+                            Self::from_trade(trade, &holding, MatchStatus::InsufficientHoldings);
+                        row.average_cost = None;
+                        rows.push(row);
+                    }
+                    Some(TradeSide::Sell) => {
+                        let average_cost = holding.average_cost();
+                        holding.shares -= shares;
+                        holding.cost = holding.cost_of(average_cost, holding.shares);
+                        let recomputed = amount - holding.cost_of(average_cost, shares);
+
+                        let mut row = Self::from_trade(trade, &holding, MatchStatus::Matched);
+                        row.average_cost = Some(Money::jpy(average_cost));
+                        row.recomputed_profit_and_loss = Some(Money::jpy(recomputed));
+                        row.cross_check(trade, profit_and_loss_list, &mut matched);
+                        rows.push(row);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        rows
+    }
+
+    // 銘柄ごとの現在の保有数量と平均取得単価。口座ごとの最後の約定後の値を合算し、単価は保有数量で加重平均する。
+    // 投資信託の単価も1口あたりに直す。すべて売却した銘柄は保有数量0として残す
+    pub fn current_positions(rows: &[CostBasis]) -> HashMap<String, Position> {
+        let mut latest: HashMap<(String, AccountType), Holding> = HashMap::new();
+        for row in rows {
+            let key = row
+                .security_code
+                .clone()
+                .or_else(|| row.security_name.clone())
+                .unwrap_or_default();
+            let shares = Decimal::from(row.holding_shares.unwrap_or_default());
+            let average_cost = row
+                .average_cost
+                .as_ref()
+                .map_or(Decimal::ZERO, |m| m.amount);
+            let holding = Holding {
+                shares,
+                ..Holding::new(row.price_units)
+            };
+            latest.insert(
+                (key, AccountType::parse(row.account.as_deref())),
+                Holding {
+                    cost: holding.cost_of(average_cost, shares),
+                    ..holding
+                },
+            );
+        }
+
+        let mut totals: HashMap<String, Holding> = HashMap::new();
+        for ((key, _), holding) in latest {
+            let total = totals
+                .entry(key)
+                .or_insert_with(|| Holding::new(holding.price_units));
+            total.shares += holding.shares;
+            total.cost += holding.cost;
+        }
+        totals
+            .into_iter()
+            .map(|(key, total)| {
+                let average_cost = match total.shares.is_zero() {
+                    true => Decimal::ZERO,
+                    false => total.cost / total.shares,
+                };
+                let position = Position {
+                    shares: total.shares,
+                    average_cost,
+                };
+                (key, position)
+            })
+            .collect()
+    }
+
+    // 約定日・口座区分・銘柄・数量が一致する実現損益の明細と比べ、1円以上の差があれば不一致とする
+    fn cross_check(
+        &mut self,
+        trade: &TradeHistory,
+        profit_and_loss_list: &[ProfitAndLoss],
+        matched: &mut [bool],
+    ) {
+        let found = profit_and_loss_list
+            .iter()
+            .enumerate()
+            .find(|(index, profit_and_loss)| {
+                !matched[*index]
+                    && profit_and_loss.trade_date == trade.trade_date
+                    && profit_and_loss.account_type() == trade.account_type()
+                    && profit_and_loss.shares == trade.shares
+                    && match (&profit_and_loss.security_code, &trade.security_code) {
+                        (Some(code), Some(trade_code)) => code == trade_code,
+                        _ => profit_and_loss.security_name == trade.security_name,
+                    }
+            });
+        let Some((index, profit_and_loss)) = found else {
+            self.status = MatchStatus::MissingProfitAndLoss;
+            return;
+        };
+        matched[index] = true;
+
+        let amount = |money: &Option<Money>| money.as_ref().map(|m| m.amount);
+        let difference = amount(&profit_and_loss.realized_profit_and_loss)
+            .zip(amount(&self.recomputed_profit_and_loss))
+            .map(|(reported, recomputed)| reported - recomputed);
+        let price_difference = amount(&profit_and_loss.purchase_price)
+            .zip(amount(&self.average_cost))
+            .map(|(reported, recomputed)| reported - recomputed);
+
+        self.purchase_price = profit_and_loss.purchase_price.clone();
+        self.realized_profit_and_loss = profit_and_loss.realized_profit_and_loss.clone();
+        self.difference = difference.map(Money::jpy);
+        let is_mismatched = [difference, price_difference]
+            .into_iter()
+            .flatten()
+            .any(|difference| difference.abs() >= Decimal::ONE);
+        if is_mismatched {
+            self.status = MatchStatus::Mismatched;
+        }
+    }
+
+    pub fn get_all_fields(&self) -> Vec<(String, Option<String>)> {
+        let money = |m: &Option<Money>| m.as_ref().map(|m| m.to_string());
+        vec![
+            (
+                "trade_date".to_string(),
+                self.trade_date.map(|d| d.to_string()),
+            ),
+            ("security_code".to_string(), self.security_code.clone()),
+            ("security_name".to_string(), self.security_name.clone()),
+            ("account".to_string(), self.account.clone()),
+            ("trade_type".to_string(), self.trade_type.clone()),
+            ("shares".to_string(), self.shares.map(|s| s.to_string())),
+            (
+                "settlement_amount".to_string(),
+                money(&self.settlement_amount),
+            ),
+            (
+                "holding_shares".to_string(),
+                self.holding_shares.map(|s| s.to_string()),
+            ),
+            ("average_cost".to_string(), money(&self.average_cost)),
+            ("purchase_price".to_string(), money(&self.purchase_price)),
+            (
+                "recomputed_profit_and_loss".to_string(),
+                money(&self.recomputed_profit_and_loss),
+            ),
+            (
+                "realized_profit_and_loss".to_string(),
+                money(&self.realized_profit_and_loss),
+            ),
+            ("difference".to_string(), money(&self.difference)),
+            ("status".to_string(), Some(self.status.label().to_string())),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(
+        trade_date: NaiveDate,
+        security_code: Option<&str>,
+        trade_type: &str,
+        shares: i32,
+        settlement_amount: i64,
+    ) -> TradeHistory {
+        TradeHistory {
+            trade_date: Some(trade_date),
+            security_code: security_code.map(|code| code.to_string()),
+            security_name: Some("テスト銘柄".to_string()),
+            account: Some("特定".to_string()),
+            trade_type: Some(trade_type.to_string()),
+            shares: Some(shares),
+            settlement_amount: Some(Money::jpy(Decimal::from(settlement_amount))),
+            ..TradeHistory::new()
+        }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn amount(money: &Option<Money>) -> Option<Decimal> {
+        money.as_ref().map(|m| m.amount)
+    }
+
+    #[test]
+    fn fund_average_cost_is_per_ten_thousand_units() {
+        let trades = [
+            trade(date(2024, 1, 5), None, "投信金額買付", 500_000, 1_000_000),
+            trade(date(2024, 6, 5), None, "投信金額買付", 300_000, 630_000),
+            trade(date(2025, 2, 3), None, "売却", 400_000, 900_000),
+        ];
+        let profit_and_loss = ProfitAndLoss {
+            trade_date: Some(date(2025, 2, 3)),
+            security_name: Some("テスト銘柄".to_string()),
+            account: Some("特定".to_string()),
+            shares: Some(400_000),
+            realized_profit_and_loss: Some(Money::jpy(Decimal::from(85_000))),
+            ..ProfitAndLoss::new()
+        };
+        let rows = CostBasis::calculate(&trades, &[profit_and_loss]);
+
+        // (1,000,000 + 630,000) / 800,000口 × 10,000 = 20,375円
+        let sell = &rows[2];
+        assert_eq!(amount(&sell.average_cost), Some(Decimal::from(20_375)));
+        assert_eq!(
+            amount(&sell.recomputed_profit_and_loss),
+            Some(Decimal::from(85_000))
+        );
+        assert_eq!(sell.status, MatchStatus::Matched);
+
+        // 保有し続けている分の1口あたりの取得単価
+        let position = CostBasis::current_positions(&rows)["テスト銘柄"];
+        assert_eq!(position.shares, Decimal::from(400_000));
+        assert_eq!(position.average_cost, Decimal::new(20375, 4));
+    }
+
+    #[test]
+    fn fund_trade_amount_falls_back_to_price_per_ten_thousand_units() {
+        let trade = TradeHistory {
+            unit_price: Some(Money::jpy(Decimal::from(20_000))),
+            settlement_amount: None,
+            ..trade(date(2024, 1, 5), None, "投信金額買付", 500_000, 0)
+        };
+        assert_eq!(trade.trade_amount(), Some(Decimal::from(1_000_000)));
+    }
+
+    #[test]
+    fn fund_redemption_reduces_position() {
+        let trades = [
+            trade(date(2024, 1, 5), None, "投信金額買付", 500_000, 1_000_000),
+            trade(date(2024, 12, 10), None, "再投資", 10_000, 21_000),
+            trade(date(2025, 2, 3), None, "解約", 210_000, 450_000),
+        ];
+        let rows = CostBasis::calculate(&trades, &[]);
+
+        // (1,000,000 + 21,000) / 510,000口 × 10,000 = 20,019.6… → 20,020円
+        let redemption = &rows[2];
+        assert_eq!(
+            amount(&redemption.average_cost),
+            Some(Decimal::from(20_020))
+        );
+        assert_eq!(
+            amount(&redemption.recomputed_profit_and_loss),
+            Some(Decimal::from(450_000 - 420_420))
+        );
+        assert_eq!(redemption.holding_shares, Some(300_000));
+        assert_eq!(redemption.status, MatchStatus::MissingProfitAndLoss);
+    }
+
+    #[test]
+    fn same_day_buy_is_counted_before_sell_in_newest_first_csv() {
+        // 新しい約定が先に並ぶCSVでは、同じ日の売却が買付より前の行になる
+        let trades = [
+            trade(date(2025, 3, 3), Some("7203"), "現物売", 100, 105_000),
+            trade(date(2025, 3, 3), Some("7203"), "現物買", 100, 100_000),
+        ];
+        let profit_and_loss_list = [stock_profit_and_loss(date(2025, 3, 3), 100, 5_000)];
+        let rows = CostBasis::calculate(&trades, &profit_and_loss_list);
+
+        assert_eq!(rows[0].status, MatchStatus::Purchase);
+        let sell = &rows[1];
+        assert_eq!(sell.status, MatchStatus::Matched);
+        assert_eq!(amount(&sell.average_cost), Some(Decimal::from(1_000)));
+        assert_eq!(
+            amount(&sell.recomputed_profit_and_loss),
+            Some(Decimal::from(5_000))
+        );
+        assert_eq!(sell.holding_shares, Some(0));
+    }
+
+    fn stock_profit_and_loss(trade_date: NaiveDate, shares: i32, realized: i64) -> ProfitAndLoss {
+        ProfitAndLoss {
+            trade_date: Some(trade_date),
+            security_code: Some("7203".to_string()),
+            account: Some("特定".to_string()),
+            shares: Some(shares),
+            realized_profit_and_loss: Some(Money::jpy(Decimal::from(realized))),
+            ..ProfitAndLoss::new()
+        }
+    }
+
+    #[test]
+    fn stock_average_cost_is_a_moving_average_rounded_up() {
+        let trades = [
+            trade(date(2025, 1, 6), Some("7203"), "現物買", 100, 100_055),
+            trade(date(2025, 2, 3), Some("7203"), "現物買", 100, 120_000),
+            trade(date(2025, 3, 3), Some("7203"), "現物売", 100, 150_000),
+            trade(date(2025, 4, 1), Some("7203"), "現物買", 100, 90_000),
+            trade(date(2025, 5, 1), Some("7203"), "現物売", 200, 210_000),
+        ];
+        let profit_and_loss_list = [
+            stock_profit_and_loss(date(2025, 3, 3), 100, 39_900),
+            stock_profit_and_loss(date(2025, 5, 1), 200, 10_000),
+        ];
+        let rows = CostBasis::calculate(&trades, &profit_and_loss_list);
+
+        // (100,055 + 120,000) / 200株 = 1,100.275 → 1,101円。150,000 - 1,101 × 100 = 39,900円
+        let first_sell = &rows[2];
+        assert_eq!(amount(&first_sell.average_cost), Some(Decimal::from(1_101)));
+        assert_eq!(
+            amount(&first_sell.recomputed_profit_and_loss),
+            Some(Decimal::from(39_900))
+        );
+        assert_eq!(first_sell.holding_shares, Some(100));
+        assert_eq!(first_sell.status, MatchStatus::Matched);
+
+        // 残りの取得価額 1,101 × 100 = 110,100円に買い増し分を足して割り直す。
+        // (110,100 + 90,000) / 200株 = 1,000.5 → 1,001円
+        let second_sell = &rows[4];
+        assert_eq!(
+            amount(&second_sell.average_cost),
+            Some(Decimal::from(1_001))
+        );
+        assert_eq!(
+            amount(&second_sell.recomputed_profit_and_loss),
+            Some(Decimal::from(9_800))
+        );
+        assert_eq!(amount(&second_sell.difference), Some(Decimal::from(200)));
+        assert_eq!(second_sell.status, MatchStatus::Mismatched);
+
+        let position = CostBasis::current_positions(&rows)["7203"];
+        assert_eq!(position.shares, Decimal::ZERO);
+    }
+
+    #[test]
+    fn selling_more_than_held_is_reported_as_insufficient_holdings() {
+        let trades = [
+            trade(date(2025, 1, 6), Some("7203"), "現物買", 100, 100_000),
+            trade(date(2025, 3, 3), Some("7203"), "現物売", 200, 250_000),
+        ];
+        let rows = CostBasis::calculate(&trades, &[]);
+
+        let sell = &rows[1];
+        assert_eq!(sell.status, MatchStatus::InsufficientHoldings);
+        assert_eq!(sell.average_cost, None);
+        assert_eq!(sell.recomputed_profit_and_loss, None);
+    }
+}
@@ -0,0 +1,160 @@
+use super::{
+    super::broker::lib::BrokerAdapter,
+    super::common::ParseDiagnostic,
+    super::csv::column_map::{ColumnMap, ColumnSpec},
+    super::profit_and_loss::profit_and_loss::ProfitAndLoss,
+    super::store::lib::{HistoryQuery, ImportSummary, UserStore},
+    super::templete::{TableRenderer, TableView, TemplateManager, TemplateStruct},
+    cost_basis::{CostBasis, MatchStatus},
+    trade_history::TradeHistory,
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use csv::StringRecord;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{cell::RefCell, collections::BTreeMap};
+use tera::{Context, Tera};
+
+// 照合結果の件数
+#[derive(Debug, Default, Serialize)]
+struct MatchSummary {
+    matched: usize,
+    mismatched: usize,
+    unmatched: usize,
+}
+
+// 約定履歴から取得価額を計算し直し、保存済みの実現損益の明細と照合する
+pub struct TradeHistoryManager {
+    template_struct: TemplateStruct,
+    trade_history_map: RefCell<BTreeMap<NaiveDate, Vec<TradeHistory>>>,
+    profit_and_loss_list: RefCell<Vec<ProfitAndLoss>>,
+}
+
+impl TradeHistoryManager {
+    pub fn new() -> Self {
+        TradeHistoryManager {
+            template_struct: TemplateStruct::new(),
+            trade_history_map: RefCell::new(BTreeMap::new()),
+            profit_and_loss_list: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn insert(&self, trade_date: NaiveDate, trade: TradeHistory) {
+        self.trade_history_map
+            .borrow_mut()
+            .entry(trade_date)
+            .or_default()
+            .push(trade);
+    }
+
+    fn calculate_cost_basis(&self) -> Vec<CostBasis> {
+        let trade_history = self
+            .trade_history_map
+            .borrow()
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        CostBasis::calculate(&trade_history, &self.profit_and_loss_list.borrow())
+    }
+
+    fn summarize(&self, cost_basis_list: &[CostBasis]) -> MatchSummary {
+        let mut summary = MatchSummary::default();
+        for cost_basis in cost_basis_list {
+            match cost_basis.status {
+                MatchStatus::Purchase => {}
+                MatchStatus::Matched => summary.matched += 1,
+                MatchStatus::Mismatched => summary.mismatched += 1,
+                MatchStatus::MissingProfitAndLoss | MatchStatus::InsufficientHoldings => {
+                    summary.unmatched += 1
+                }
+            }
+        }
+        summary
+    }
+}
+
+impl TableRenderer for TradeHistoryManager {
+    fn template_struct(&self) -> &TemplateStruct {
+        &self.template_struct
+    }
+}
+
+impl TemplateManager for TradeHistoryManager {
+    // 取得価額は過去の買付から積み上げるため、保存した後に保存済みの約定をすべて読み直す
+    fn persist(&self, store: &UserStore) -> Result<()> {
+        let summary = self.save(store)?;
+        self.template_struct().metadata.borrow_mut().stored = Some(summary);
+        self.trade_history_map.borrow_mut().clear();
+        self.load(store, &HistoryQuery::default())
+    }
+
+    fn column_specs(&self, broker: &dyn BrokerAdapter) -> &'static [ColumnSpec] {
+        broker.trade_history_columns()
+    }
+
+    fn process_records(&self, records: Vec<StringRecord>, columns: &ColumnMap) {
+        let mut diagnostics = self.template_struct().diagnostics.borrow_mut();
+        for record in records {
+            let trade = TradeHistory::from_record(&record, columns, &mut diagnostics);
+            let Some(trade_date) = trade.trade_date else {
+                ParseDiagnostic::push_row_skipped(
+                    &mut diagnostics,
+                    &record,
+                    columns,
+                    "trade_date",
+                    "Row skipped: missing trade date",
+                );
+                continue;
+            };
+            self.insert(trade_date, trade);
+        }
+    }
+
+    fn save(&self, store: &UserStore) -> Result<ImportSummary> {
+        store.save_trade_history(self.trade_history_map.borrow().values().flatten())
+    }
+
+    // 照合には、実現損益のタブで取り込んで保存した明細を使う
+    fn load(&self, store: &UserStore, query: &HistoryQuery) -> Result<()> {
+        for trade in store.load_trade_history(query)? {
+            if let Some(trade_date) = trade.trade_date {
+                self.insert(trade_date, trade);
+            }
+        }
+        *self.profit_and_loss_list.borrow_mut() = store.load_profit_and_loss(query)?;
+        Ok(())
+    }
+
+    fn generate_html_table(&self, tera: &Tera) -> Result<String> {
+        let cost_basis_list = self.calculate_cost_basis();
+        let headers = self.generate_table_header(CostBasis::new().get_all_fields());
+        let rows = cost_basis_list
+            .iter()
+            .map(|cost_basis| {
+                let tr_class = match cost_basis.status {
+                    MatchStatus::Mismatched => "discrepancy",
+                    MatchStatus::MissingProfitAndLoss | MatchStatus::InsufficientHoldings => {
+                        "unmatched"
+                    }
+                    MatchStatus::Purchase | MatchStatus::Matched => "",
+                };
+                self.generate_table_row_with_class(tr_class, &cost_basis.get_all_fields())
+            })
+            .collect();
+
+        let mut context = Context::new();
+        context.insert("table", &TableView { headers, rows });
+        context.insert("summary", &self.summarize(&cost_basis_list));
+        self.render_context(tera, "partials/trade_history_table.html", context)
+    }
+
+    fn generate_json(&self) -> Result<Value> {
+        let cost_basis_list = self.calculate_cost_basis();
+        Ok(json!({
+            "summary": self.summarize(&cost_basis_list),
+            "rows": cost_basis_list,
+        }))
+    }
+}
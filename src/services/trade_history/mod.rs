@@ -0,0 +1,4 @@
+pub mod cost_basis;
+pub mod lib;
+#[allow(clippy::module_inception)]
+pub mod trade_history;
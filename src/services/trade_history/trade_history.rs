@@ -0,0 +1,187 @@
+use chrono::NaiveDate;
+use csv::StringRecord;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::services::{
+    account::AccountType,
+    common::{ParseDiagnostic, RecordReader},
+    csv::column_map::ColumnMap,
+    money::{Currency, Money},
+};
+
+// 投資信託の約定単価・基準価額の単位口数
+pub const FUND_PRICE_UNITS: Decimal = Decimal::from_parts(10000, 0, 0, false, 0);
+
+// 投資信託の取引を表す取引欄の表記
+const FUND_TRADE_TYPES: [&str; 5] = ["投信", "解約", "買取請求", "再投資", "積立"];
+
+// 売買の別
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+// 約定履歴の1件
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeHistory {
+    pub trade_date: Option<NaiveDate>,      // 約定日
+    pub settlement_date: Option<NaiveDate>, // 受渡日
+    pub security_code: Option<String>,      // 銘柄コード
+    pub security_name: Option<String>,      // 銘柄名
+    pub account: Option<String>,            // 口座
+    pub trade_type: Option<String>, // 取引（「買付」「株式現物売」など証券会社の表記のまま）
+    pub shares: Option<i32>,        // 数量[株/口]
+    pub unit_price: Option<Money>,  // 約定単価
+    pub fees: Option<Money>,        // 手数料
+    pub settlement_amount: Option<Money>, // 受渡金額
+}
+
+impl TradeHistory {
+    pub fn new() -> Self {
+        TradeHistory {
+            trade_date: None,
+            settlement_date: None,
+            security_code: None,
+            security_name: None,
+            account: None,
+            trade_type: None,
+            shares: None,
+            unit_price: None,
+            fees: None,
+            settlement_amount: None,
+        }
+    }
+
+    pub fn from_record(
+        record: &StringRecord,
+        columns: &ColumnMap,
+        diagnostics: &mut Vec<ParseDiagnostic>,
+    ) -> Self {
+        let mut reader = RecordReader::new(record, columns, diagnostics);
+        let jpy = Currency::jpy();
+        TradeHistory {
+            trade_date: reader.date("trade_date"),
+            settlement_date: reader.date("settlement_date"),
+            security_code: reader.string("security_code"),
+            security_name: reader.string("security_name"),
+            account: reader.string("account"),
+            trade_type: reader.string("trade_type"),
+            shares: reader.int("shares"),
+            unit_price: reader.money("unit_price", &jpy),
+            fees: reader.money("fees", &jpy),
+            settlement_amount: reader.money("settlement_amount", &jpy),
+        }
+    }
+
+    pub fn account_type(&self) -> AccountType {
+        AccountType::parse(self.account.as_deref())
+    }
+
+    // 信用取引の返済や現引・現渡など、売買のどちらとも判定できない取引はNone。
+    // 投資信託の解約・買取請求は売却、分配金の再投資は買付として扱う
+    pub fn side(&self) -> Option<TradeSide> {
+        let trade_type = self.trade_type.as_deref()?;
+        if trade_type.contains("信用") || trade_type.contains("返済") {
+            None
+        } else if trade_type.contains("解約") || trade_type.contains("買取請求") {
+            Some(TradeSide::Sell)
+        } else if trade_type.contains("再投資") {
+            Some(TradeSide::Buy)
+        } else if trade_type.contains('売') {
+            Some(TradeSide::Sell)
+        } else if trade_type.contains('買') {
+            Some(TradeSide::Buy)
+        } else {
+            None
+        }
+    }
+
+    // 単価が何口あたりの価格か。投資信託は1万口あたり、株式は1株あたり。
+    // 取引欄が投資信託の取引を表すか、銘柄コードのない銘柄は投資信託とみなす
+    pub fn price_units(&self) -> Decimal {
+        let is_fund = self.security_code.as_deref().is_none_or(str::is_empty)
+            || self.trade_type.as_deref().is_some_and(|trade_type| {
+                FUND_TRADE_TYPES
+                    .iter()
+                    .any(|fund_trade_type| trade_type.contains(fund_trade_type))
+            });
+        match is_fund {
+            true => FUND_PRICE_UNITS,
+            false => Decimal::ONE,
+        }
+    }
+
+    // 同じ銘柄をまとめるためのキー。投資信託は銘柄コードがないことが多いため銘柄名で代用する
+    pub fn security_key(&self) -> String {
+        self.security_code
+            .clone()
+            .or_else(|| self.security_name.clone())
+            .unwrap_or_default()
+    }
+
+    // 約定金額。投資信託の単価は1万口あたりのため、受渡金額から手数料を除いて求める
+    pub fn trade_amount(&self) -> Option<Decimal> {
+        let fees = self.fees.as_ref().map_or(Decimal::ZERO, |fees| fees.amount);
+        match (&self.settlement_amount, self.side()) {
+            (Some(amount), Some(TradeSide::Buy)) => Some(amount.amount.abs() - fees),
+            (Some(amount), Some(TradeSide::Sell)) => Some(amount.amount.abs() + fees),
+            _ => {
+                let unit_price = self.unit_price.as_ref()?.amount;
+                Some(unit_price * Decimal::from(self.shares?) / self.price_units())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn side(security_code: Option<&str>, trade_type: &str) -> Option<TradeSide> {
+        TradeHistory {
+            security_code: security_code.map(|code| code.to_string()),
+            trade_type: Some(trade_type.to_string()),
+            ..TradeHistory::new()
+        }
+        .side()
+    }
+
+    #[test]
+    fn stock_trade_types() {
+        assert_eq!(side(Some("7203"), "株式現物買"), Some(TradeSide::Buy));
+        assert_eq!(side(Some("7203"), "株式現物売"), Some(TradeSide::Sell));
+        assert_eq!(side(Some("7203"), "信用返済買"), None);
+        assert_eq!(side(Some("7203"), "現引"), None);
+    }
+
+    #[test]
+    fn fund_trade_types() {
+        assert_eq!(side(None, "投信金額買付"), Some(TradeSide::Buy));
+        assert_eq!(side(None, "積立買付"), Some(TradeSide::Buy));
+        assert_eq!(side(None, "再投資"), Some(TradeSide::Buy));
+        assert_eq!(side(None, "解約"), Some(TradeSide::Sell));
+        assert_eq!(side(None, "買取請求"), Some(TradeSide::Sell));
+    }
+
+    #[test]
+    fn price_units_distinguish_funds_from_stocks() {
+        let trade = |security_code: Option<&str>, trade_type: &str| TradeHistory {
+            security_code: security_code.map(|code| code.to_string()),
+            trade_type: Some(trade_type.to_string()),
+            ..TradeHistory::new()
+        };
+        assert_eq!(
+            trade(Some("7203"), "株式現物買").price_units(),
+            Decimal::ONE
+        );
+        assert_eq!(trade(None, "解約").price_units(), FUND_PRICE_UNITS);
+        assert_eq!(trade(Some(""), "売却").price_units(), FUND_PRICE_UNITS);
+        assert_eq!(
+            trade(Some("0331418A"), "投信金額買付").price_units(),
+            FUND_PRICE_UNITS
+        );
+    }
+}
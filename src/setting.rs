@@ -1,4 +1,5 @@
 use once_cell::sync::Lazy;
+use rust_decimal::Decimal;
 use std::collections::HashMap;
 
 // 型エイリアスを定義
@@ -24,6 +25,16 @@ pub static HEADERS: Lazy<HeaderMap> = Lazy::new(|| {
         "total_realized_profit_and_loss".to_string(),
         "合計実現損益".to_string(),
     );
+    map.insert(
+        "cumulative_realized_profit_and_loss".to_string(),
+        "年間累計損益(特定)".to_string(),
+    );
+    map.insert(
+        "cumulative_withholding_tax".to_string(),
+        "年間累計源泉徴収税額".to_string(),
+    );
+    map.insert("income_tax".to_string(), "所得税".to_string());
+    map.insert("resident_tax".to_string(), "住民税".to_string());
     map.insert("withholding_tax".to_string(), "源泉徴収税額".to_string());
     map.insert("profit_and_loss".to_string(), "損益".to_string());
     map.insert("product".to_string(), "商品".to_string());
@@ -34,18 +45,140 @@ pub static HEADERS: Lazy<HeaderMap> = Lazy::new(|| {
         "配当・分配金(税引前)".to_string(),
     );
     map.insert("taxes".to_string(), "税額".to_string());
+    map.insert("foreign_taxes".to_string(), "外国源泉税".to_string());
+    map.insert("domestic_taxes".to_string(), "国内源泉税".to_string());
     map.insert("net_amount_received".to_string(), "受取金額".to_string());
     map.insert(
         "total_dividends_before_tax".to_string(),
         "配当・分配金合計(税引前)".to_string(),
     );
     map.insert("total_taxes".to_string(), "税額合計".to_string());
+    map.insert("year".to_string(), "年".to_string());
+    map.insert("netted_income".to_string(), "損益通算後の所得".to_string());
+    map.insert(
+        "netted_withholding_tax".to_string(),
+        "損益通算後の税額".to_string(),
+    );
+    map.insert("expected_refund".to_string(), "還付見込額".to_string());
+    map.insert("section".to_string(), "区分".to_string());
+    map.insert("item".to_string(), "項目".to_string());
+    map.insert("specific_account".to_string(), "特定口座".to_string());
+    map.insert("general_account".to_string(), "一般口座".to_string());
+    map.insert("total".to_string(), "合計".to_string());
+    map.insert("filed".to_string(), "申告".to_string());
+    map.insert("transfer_income".to_string(), "譲渡所得等".to_string());
+    map.insert("dividend_income".to_string(), "配当所得".to_string());
+    map.insert(
+        "carried_in_loss".to_string(),
+        "前年からの繰越損失".to_string(),
+    );
+    map.insert("expired_loss".to_string(), "期限切れの損失".to_string());
+    map.insert("used_loss".to_string(), "本年の控除額".to_string());
+    map.insert("new_loss".to_string(), "本年の損失".to_string());
+    map.insert(
+        "carried_out_loss".to_string(),
+        "翌年への繰越損失".to_string(),
+    );
+    map.insert("taxable_income".to_string(), "控除後の所得".to_string());
+    map.insert("origin_year".to_string(), "発生年".to_string());
+    map.insert("remaining_loss".to_string(), "繰越残高".to_string());
+    map.insert("expiry_year".to_string(), "控除期限".to_string());
     map.insert(
         "total_net_amount_received".to_string(),
         "受取金額".to_string(),
     );
+    map.insert("trade_type".to_string(), "取引".to_string());
+    map.insert("settlement_amount".to_string(), "受渡金額".to_string());
+    map.insert("holding_shares".to_string(), "保有数量".to_string());
+    map.insert(
+        "average_cost".to_string(),
+        "平均取得単価(再計算)".to_string(),
+    );
+    map.insert(
+        "recomputed_profit_and_loss".to_string(),
+        "実現損益(再計算)".to_string(),
+    );
+    map.insert("difference".to_string(), "差額".to_string());
+    map.insert("status".to_string(), "照合結果".to_string());
+    map.insert("frame".to_string(), "投資枠".to_string());
+    map.insert("acquisition_price".to_string(), "平均取得単価".to_string());
+    map.insert("current_price".to_string(), "現在値".to_string());
+    map.insert("acquisition_amount".to_string(), "取得金額".to_string());
+    map.insert("valuation".to_string(), "評価額".to_string());
+    map.insert(
+        "unrealized_profit_and_loss".to_string(),
+        "評価損益".to_string(),
+    );
+    map.insert(
+        "unrealized_profit_and_loss_rate".to_string(),
+        "評価損益率[%]".to_string(),
+    );
+    map.insert("payments".to_string(), "支払回数".to_string());
+    map.insert(
+        "dividend_per_share".to_string(),
+        "1株あたり配当(税引前)".to_string(),
+    );
+    map.insert("dividend_growth_rate".to_string(), "前年比[%]".to_string());
+    map.insert("cost_per_share".to_string(), "取得単価".to_string());
+    map.insert("yield_on_cost".to_string(), "取得利回り[%]".to_string());
+    map.insert("payment_month".to_string(), "入金予定月".to_string());
+    map.insert("payments_per_year".to_string(), "年間支払回数".to_string());
+    map.insert(
+        "last_settlement_date".to_string(),
+        "前回の入金日".to_string(),
+    );
+    map.insert(
+        "projected_dividend".to_string(),
+        "予想配当(税引前)".to_string(),
+    );
+    map.insert("fx_rate".to_string(), "為替レート(TTM)".to_string());
+    map.insert("fx_rate_date".to_string(), "換算日".to_string());
+    map.insert("payment_date".to_string(), "支払確定日".to_string());
+    map.insert(
+        "jpy_dividends_before_tax".to_string(),
+        "配当・分配金(税引前・円換算)".to_string(),
+    );
+    map.insert("jpy_taxes".to_string(), "税額(円換算)".to_string());
+    map.insert(
+        "jpy_net_amount_received".to_string(),
+        "受取金額(円換算)".to_string(),
+    );
+    map.insert("rate_date".to_string(), "適用日".to_string());
+    map.insert("currency_code".to_string(), "通貨".to_string());
+    map.insert("ttm".to_string(), "TTM[円]".to_string());
+    map.insert(
+        "jpy_foreign_taxes".to_string(),
+        "外国源泉税(円換算)".to_string(),
+    );
+    map.insert("missing_rates".to_string(), "レートなし件数".to_string());
+    map.insert(
+        "settlement_date_rates".to_string(),
+        "入金日で換算した件数".to_string(),
+    );
+    map.insert("annual_limit".to_string(), "年間投資枠".to_string());
+    map.insert("annual_used".to_string(), "年間の買付額".to_string());
+    map.insert(
+        "annual_remaining".to_string(),
+        "年間投資枠の残り".to_string(),
+    );
+    map.insert(
+        "restored_capacity".to_string(),
+        "売却で復活した枠".to_string(),
+    );
+    map.insert("lifetime_limit".to_string(), "生涯投資枠".to_string());
+    map.insert(
+        "lifetime_used".to_string(),
+        "生涯投資枠の利用額(簿価)".to_string(),
+    );
+    map.insert(
+        "lifetime_remaining".to_string(),
+        "生涯投資枠の残り".to_string(),
+    );
     map
 });
 
-// tax_rateの定義
-pub const TAX_RATE: f64 = 0.20315;
+// 上場株式等の譲渡所得・配当所得に対する税率の定義
+// 所得税15%に復興特別所得税（所得税額の2.1%）を加えた15.315%
+pub const INCOME_TAX_RATE: Decimal = Decimal::from_parts(15315, 0, 0, false, 5);
+// 住民税5%
+pub const RESIDENT_TAX_RATE: Decimal = Decimal::from_parts(5, 0, 0, false, 2);